@@ -0,0 +1,26 @@
+use std::fmt::Display;
+
+/// Data files read at startup, so that they can be changed without rebuilding. Each one has
+/// a copy built into the executable, used when the file is missing or broken.
+pub async fn load_or_embedded<T, E: Display>(path: &str, embedded: &[u8], parse: impl Fn(&[u8]) -> Result<T, E>) -> T {
+    let data = macroquad::file::load_file(path).await.map_err(|err| err.to_string());
+    parsed_or_embedded(path, data, embedded, parse)
+}
+
+/// Same as `load_or_embedded` for the command line, which has no macroquad context.
+pub fn read_or_embedded<T, E: Display>(path: &str, embedded: &[u8], parse: impl Fn(&[u8]) -> Result<T, E>) -> T {
+    let data = std::fs::read(path).map_err(|err| err.to_string());
+    parsed_or_embedded(path, data, embedded, parse)
+}
+
+fn parsed_or_embedded<T, E: Display>(path: &str, data: Result<Vec<u8>, String>, embedded: &[u8], parse: impl Fn(&[u8]) -> Result<T, E>) -> T {
+    match data {
+        Ok(data) => match parse(&data) {
+            Ok(value) => return value,
+            Err(err) => macroquad::logging::warn!("could not read {}: {}", path, err),
+        },
+        Err(err) => macroquad::logging::warn!("could not load {}: {}", path, err),
+    }
+
+    parse(embedded).unwrap_or_else(|err| panic!("the embedded copy of {} is invalid: {}", path, err))
+}
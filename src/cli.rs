@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::construction;
+use crate::cubes::{create_cubemap, read_cube_model, Cube, CubeMap, FaceColors};
+use crate::equivalence::{CellDifference, Equivalence};
+use crate::exercise::Exercise;
+use crate::grid::CubeGrid;
+use crate::palette::read_palette;
+use crate::projection::{Projection, ProjectionView};
+use crate::support::{self, SupportRule};
+use crate::worksheet;
+
+const USAGE: &str = "\
+usage:
+    lacu                                      open the cube builder
+    lacu validate <file>...                   check that the construction files can be loaded
+    lacu projections <file> [--svg <output>]  print the three views of a construction,
+                                              or save them as an SVG picture
+    lacu compare [--rotated | --mirrored] <target> <submission>...
+                                              check the submissions against the target and
+                                              print the result as JSON; a folder stands for
+                                              every .ron file inside it. With --rotated the
+                                              target may also be moved and turned, with
+                                              --mirrored also mirrored, and the cells that
+                                              differ are listed
+    lacu help                                 show this message";
+
+/// Everything the command line needs to know about a submission.
+#[derive(Serialize)]
+struct Submission {
+    file: String,
+    correct: bool,
+    wrong_views: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    differences: Vec<Difference>,
+    error: Option<String>,
+}
+
+/// A cell where the submission does not match the target, in the submission grid.
+#[derive(Serialize)]
+struct Difference {
+    x: usize,
+    y: usize,
+    z: usize,
+    expected: Option<CellCube>,
+    found: Option<CellCube>,
+}
+
+/// Same fields as the cubes of the construction files.
+#[derive(Serialize)]
+struct CellCube {
+    cube: String,
+    rotation: usize,
+}
+
+impl Difference {
+    fn new(difference: &CellDifference, cubemap: &CubeMap) -> Self {
+        let cell_cube = |cube: Cube| CellCube { cube: cubemap[cube].name.clone(), rotation: cube.quarter_turns() };
+        Difference {
+            x: difference.x,
+            y: difference.y,
+            z: difference.z,
+            expected: difference.expected.map(cell_cube),
+            found: difference.found.map(cell_cube),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Comparison {
+    target: String,
+    submissions: Vec<Submission>,
+}
+
+/// Runs the command given on the command line and returns the exit code of the process.
+pub fn run(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["validate", files @ ..] if !files.is_empty() => validate(files),
+        ["projections", file] => projections(file, None),
+        ["projections", file, "--svg", output] => projections(file, Some(output)),
+        ["compare", "--rotated", target, submissions @ ..] if !submissions.is_empty() => {
+            compare(target, submissions, Some(Equivalence::Rotation))
+        }
+        ["compare", "--mirrored", target, submissions @ ..] if !submissions.is_empty() => {
+            compare(target, submissions, Some(Equivalence::RotationOrMirroring))
+        }
+        ["compare", target, submissions @ ..] if !submissions.is_empty() => compare(target, submissions, None),
+        ["help"] | ["--help"] | ["-h"] => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            2
+        }
+    }
+}
+
+fn load_cubemap() -> CubeMap {
+    create_cubemap(read_palette(), &read_cube_model())
+}
+
+/// Stable name of the view, for the JSON output.
+fn view_id(view: ProjectionView) -> &'static str {
+    match view {
+        ProjectionView::Front => "front",
+        ProjectionView::Side => "side",
+        ProjectionView::Top => "top",
+    }
+}
+
+/// Returns whether all the files are valid. Cubes that would fall down are only reported,
+/// since constructions may be built with a different support rule.
+fn validate(files: &[&str]) -> Result<bool, String> {
+    let cubemap = load_cubemap();
+    let mut all_valid = true;
+    for file in files {
+        match construction::load_from_file(file, &cubemap) {
+            Ok(grid) => {
+                let cubes = grid.iter_cubes().count();
+                println!("{}: ok, {} cubes in a {}x{}x{} grid", file, cubes, grid.width(), grid.depth(), grid.height());
+                let unsupported = support::unsupported_cubes(&grid, SupportRule::Gravity);
+                if !unsupported.is_empty() {
+                    println!("{}: {} cubes are not resting on anything", file, unsupported.len());
+                }
+            }
+            Err(err) => {
+                println!("{}: {}", file, err);
+                all_valid = false;
+            }
+        }
+    }
+
+    Ok(all_valid)
+}
+
+fn projections(file: &str, svg_output: Option<&str>) -> Result<bool, String> {
+    let cubemap = load_cubemap();
+    let grid = construction::load_from_file(file, &cubemap).map_err(|err| format!("{}: {}", file, err))?;
+    if let Some(output) = svg_output {
+        std::fs::write(output, worksheet::projections_svg(&grid, &cubemap)).map_err(|err| format!("{}: {}", output, err))?;
+        return Ok(true);
+    }
+
+    print!("{}", projections_text(&grid, &cubemap));
+    Ok(true)
+}
+
+/// Draws every view as a block of letters, one for each cell, with a legend of the colors.
+/// Empty cells are dots.
+fn projections_text(grid: &CubeGrid, cubemap: &CubeMap) -> String {
+    let mut symbols: HashMap<FaceColors, char> = HashMap::new();
+    let mut legend: Vec<(char, FaceColors)> = Vec::new();
+    let mut text = String::new();
+    for view in ProjectionView::ALL {
+        let projection = Projection::compute(grid, cubemap, view);
+        text.push_str(view.name());
+        text.push('\n');
+        for row in 0..projection.rows() {
+            let line: Vec<String> = (0..projection.columns())
+                .map(|column| match projection.get(row, column) {
+                    Some(face) => {
+                        let symbol = *symbols.entry(face).or_insert_with(|| {
+                            let symbol = (b'A' + (legend.len() % 26) as u8) as char;
+                            legend.push((symbol, face));
+                            symbol
+                        });
+                        symbol.to_string()
+                    }
+                    None => ".".to_owned(),
+                })
+                .collect();
+            text.push_str(&line.join(" "));
+            text.push('\n');
+        }
+        text.push('\n');
+    }
+    // split faces list their triangles in the top, right, bottom, left order
+    for (symbol, FaceColors(colors)) in legend {
+        let hex: Vec<String> = colors.iter()
+            .map(|color| format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b()))
+            .collect();
+        if hex.iter().all(|color| *color == hex[0]) {
+            text.push_str(&format!("{} {}\n", symbol, hex[0]));
+        } else {
+            text.push_str(&format!("{} {}\n", symbol, hex.join("/")));
+        }
+    }
+
+    text
+}
+
+/// Prints the JSON result and returns whether every submission is correct.
+fn compare(target: &str, submissions: &[&str], equivalence: Option<Equivalence>) -> Result<bool, String> {
+    let cubemap = load_cubemap();
+    let target_grid = construction::load_from_file(target, &cubemap).map_err(|err| format!("{}: {}", target, err))?;
+    let exercise = match equivalence {
+        Some(equivalence) => Exercise::with_equivalence(&target_grid, &cubemap, equivalence),
+        None => Exercise::new(&target_grid, &cubemap),
+    };
+
+    let mut files = Vec::new();
+    for submission in submissions {
+        let path = Path::new(submission);
+        if path.is_dir() {
+            let entries = std::fs::read_dir(path).map_err(|err| format!("{}: {}", submission, err))?;
+            let mut ron_files: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+                .collect();
+            ron_files.sort();
+            files.extend(ron_files);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    let submissions: Vec<Submission> = files.iter()
+        .map(|file| match construction::load_from_file(file, &cubemap) {
+            Ok(grid) => {
+                let check = exercise.check(&grid, &cubemap);
+                let differences = match &check.comparison {
+                    Some(comparison) if !check.is_solved() => comparison.differences.iter()
+                        .map(|difference| Difference::new(difference, &cubemap))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                Submission {
+                    file: file.display().to_string(),
+                    correct: check.is_solved(),
+                    wrong_views: check.wrong_views.iter().copied().map(view_id).collect(),
+                    differences,
+                    error: None,
+                }
+            }
+            Err(err) => Submission {
+                file: file.display().to_string(),
+                correct: false,
+                wrong_views: Vec::new(),
+                differences: Vec::new(),
+                error: Some(err.to_string()),
+            },
+        })
+        .collect();
+    let all_correct = submissions.iter().all(|submission| submission.correct);
+
+    let comparison = Comparison { target: target.to_owned(), submissions };
+    let json = serde_json::to_string_pretty(&comparison).map_err(|err| err.to_string())?;
+    println!("{}", json);
+
+    Ok(all_correct)
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cubes::CubeMap;
+use crate::grid::{CubeGrid, DEFAULT_GRID_SIZE, MAX_GRID_SIZE};
+use crate::i18n::{tr_in, Language};
+
+/// Version written in every construction file. Bump it whenever the layout of
+/// `ConstructionFile` changes, and keep the loader able to read older versions.
+/// Version 1 had no grid size, it was always a 4x4x4 grid.
+/// Versions 1 and 2 stored the cubes as `LegacyCube` identifiers instead of palette names.
+/// Up to version 3 cubes could not be rotated, and the default palette had a different
+/// kind for every direction of the split cube (see `renamed_cube`).
+pub const FORMAT_VERSION: u32 = 4;
+
+/// On-disk representation of a `CubeGrid`. Only the occupied cells are stored,
+/// so the file stays short and readable even for big grids.
+#[derive(Serialize, Deserialize)]
+struct ConstructionFile<C> {
+    version: u32,
+    #[serde(default = "default_size")]
+    width: usize,
+    #[serde(default = "default_size")]
+    depth: usize,
+    #[serde(default = "default_size")]
+    height: usize,
+    cubes: Vec<PlacedCube<C>>,
+}
+
+fn default_size() -> usize {
+    DEFAULT_GRID_SIZE
+}
+
+/// A cube stored in the file; `C` is the name of its kind in the palette.
+#[derive(Serialize, Deserialize)]
+struct PlacedCube<C> {
+    x: usize,
+    y: usize,
+    z: usize,
+    cube: C,
+    /// Counterclockwise quarter turns around the vertical axis
+    #[serde(default)]
+    rotation: usize,
+}
+
+/// The fixed set of cubes that existed before the palette could be configured.
+#[derive(Deserialize)]
+enum LegacyCube {
+    Green,
+    Red,
+    Blue,
+    Yellow,
+    White,
+    NorthRedWhite,
+    WestRedWhite,
+    SouthRedWhite,
+    EastRedWhite,
+    BlueRedTop,
+}
+
+impl LegacyCube {
+    /// Name of the same cube in the default palette.
+    fn name(&self) -> &'static str {
+        match self {
+            LegacyCube::Green => "Green",
+            LegacyCube::Red => "Red",
+            LegacyCube::Blue => "Blue",
+            LegacyCube::Yellow => "Yellow",
+            LegacyCube::White => "White",
+            LegacyCube::NorthRedWhite => "NorthRedWhite",
+            LegacyCube::WestRedWhite => "WestRedWhite",
+            LegacyCube::SouthRedWhite => "SouthRedWhite",
+            LegacyCube::EastRedWhite => "EastRedWhite",
+            LegacyCube::BlueRedTop => "BlueRedTop",
+        }
+    }
+}
+
+/// Kind and rotation of the cubes that were merged in the default palette.
+fn renamed_cube(name: &str) -> Option<(&'static str, usize)> {
+    match name {
+        "NorthRedWhite" => Some(("RedWhite", 0)),
+        "WestRedWhite" => Some(("RedWhite", 1)),
+        "SouthRedWhite" => Some(("RedWhite", 2)),
+        "EastRedWhite" => Some(("RedWhite", 3)),
+        _ => None,
+    }
+}
+
+/// Used to peek at the version before trying to parse the rest of the file.
+#[derive(Deserialize)]
+struct VersionHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum ConstructionError {
+    Io(std::io::Error),
+    Parse(ron::Error),
+    UnsupportedVersion(u32),
+    InvalidSize,
+    OutOfGrid { x: usize, y: usize, z: usize },
+    UnknownCube(String),
+}
+
+impl ConstructionError {
+    /// The error in the given language. The interface shows it in its own language,
+    /// while `Display` uses English for the command line and the logs.
+    pub fn message(&self, language: Language) -> String {
+        let tr = |key| tr_in(language, key);
+        match self {
+            ConstructionError::Io(err) => tr("error.io").replace("{error}", &err.to_string()),
+            ConstructionError::Parse(err) => tr("error.construction_parse").replace("{error}", &err.to_string()),
+            ConstructionError::UnsupportedVersion(version) => tr("error.construction_version").replace("{version}", &version.to_string()),
+            ConstructionError::InvalidSize => tr("error.grid_size").replace("{max}", &MAX_GRID_SIZE.to_string()),
+            ConstructionError::OutOfGrid { x, y, z } => tr("error.out_of_grid")
+                .replace("{x}", &x.to_string())
+                .replace("{y}", &y.to_string())
+                .replace("{z}", &z.to_string()),
+            ConstructionError::UnknownCube(name) => tr("error.unknown_cube").replace("{name}", name),
+        }
+    }
+}
+
+impl fmt::Display for ConstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Language::English))
+    }
+}
+
+impl From<std::io::Error> for ConstructionError {
+    fn from(err: std::io::Error) -> Self {
+        ConstructionError::Io(err)
+    }
+}
+
+impl From<ron::Error> for ConstructionError {
+    fn from(err: ron::Error) -> Self {
+        ConstructionError::Parse(err)
+    }
+}
+
+pub fn to_string(grid: &CubeGrid, cubemap: &CubeMap) -> Result<String, ConstructionError> {
+    let cubes = grid.iter_cubes()
+        .map(|(x, y, z, cube)| PlacedCube {
+            x,
+            y,
+            z,
+            cube: cubemap[cube].name.as_str(),
+            rotation: cube.quarter_turns(),
+        })
+        .collect();
+    let file = ConstructionFile {
+        version: FORMAT_VERSION,
+        width: grid.width(),
+        depth: grid.depth(),
+        height: grid.height(),
+        cubes,
+    };
+    let config = ron::ser::PrettyConfig::new().depth_limit(2);
+
+    Ok(ron::ser::to_string_pretty(&file, config)?)
+}
+
+pub fn from_str(text: &str, cubemap: &CubeMap) -> Result<CubeGrid, ConstructionError> {
+    let header: VersionHeader = ron::from_str(text)?;
+    let file: ConstructionFile<String> = match header.version {
+        1 | 2 => {
+            let legacy: ConstructionFile<LegacyCube> = ron::from_str(text)?;
+            ConstructionFile {
+                version: legacy.version,
+                width: legacy.width,
+                depth: legacy.depth,
+                height: legacy.height,
+                cubes: legacy.cubes.into_iter()
+                    .map(|placed| PlacedCube {
+                        x: placed.x,
+                        y: placed.y,
+                        z: placed.z,
+                        cube: placed.cube.name().to_owned(),
+                        rotation: 0,
+                    })
+                    .collect(),
+            }
+        }
+        3 | FORMAT_VERSION => ron::from_str(text)?,
+        version => return Err(ConstructionError::UnsupportedVersion(version)),
+    };
+
+    let valid_size = 1..=MAX_GRID_SIZE;
+    if !valid_size.contains(&file.width) || !valid_size.contains(&file.depth) || !valid_size.contains(&file.height) {
+        return Err(ConstructionError::InvalidSize);
+    }
+    let mut grid = CubeGrid::new(file.width, file.depth, file.height);
+    for placed in file.cubes {
+        if !grid.contains(placed.x, placed.y, placed.z) {
+            return Err(ConstructionError::OutOfGrid { x: placed.x, y: placed.y, z: placed.z });
+        }
+        let cube = match cubemap.find(&placed.cube) {
+            Some(cube) => cube,
+            None if file.version < 4 => {
+                let (name, rotation) = renamed_cube(&placed.cube).ok_or(ConstructionError::UnknownCube(placed.cube))?;
+                let cube = cubemap.find(name).ok_or_else(|| ConstructionError::UnknownCube(name.to_owned()))?;
+                cubemap.rotated(cube, rotation)
+            }
+            None => return Err(ConstructionError::UnknownCube(placed.cube)),
+        };
+        grid.set(placed.x, placed.y, placed.z, Some(cubemap.rotated(cube, placed.rotation)));
+    }
+
+    Ok(grid)
+}
+
+pub fn save_to_file(path: impl AsRef<Path>, grid: &CubeGrid, cubemap: &CubeMap) -> Result<(), ConstructionError> {
+    let text = to_string(grid, cubemap)?;
+    std::fs::write(path, text)?;
+
+    Ok(())
+}
+
+pub fn load_from_file(path: impl AsRef<Path>, cubemap: &CubeMap) -> Result<CubeGrid, ConstructionError> {
+    let text = std::fs::read_to_string(path)?;
+
+    from_str(&text, cubemap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubes::test_cubemap;
+
+    #[test]
+    fn saved_construction_loads_back() {
+        let cubemap = test_cubemap();
+        let red_white = cubemap.find("RedWhite").unwrap();
+        let mut grid = CubeGrid::new(3, 2, 4);
+        grid.set(0, 0, 0, cubemap.find("Red"));
+        grid.set(2, 1, 0, Some(cubemap.rotated(red_white, 1)));
+        grid.set(1, 1, 3, cubemap.find("BlueRedTop"));
+
+        let text = to_string(&grid, &cubemap).unwrap();
+        assert_eq!(from_str(&text, &cubemap).unwrap(), grid);
+    }
+
+    #[test]
+    fn version_1_split_cubes_load_as_turned_red_white() {
+        let cubemap = test_cubemap();
+        let text = "(version: 1, cubes: [(x: 0, y: 1, z: 0, cube: WestRedWhite)])";
+
+        let grid = from_str(text, &cubemap).unwrap();
+        assert_eq!((grid.width(), grid.depth(), grid.height()), (DEFAULT_GRID_SIZE, DEFAULT_GRID_SIZE, DEFAULT_GRID_SIZE));
+        let red_white = cubemap.find("RedWhite").unwrap();
+        assert_eq!(grid.get(0, 1, 0), Some(cubemap.rotated(red_white, 1)));
+        assert_eq!(grid.iter_cubes().count(), 1);
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let result = from_str("(version: 99, cubes: [])", &test_cubemap());
+        assert!(matches!(result, Err(ConstructionError::UnsupportedVersion(99))));
+    }
+
+    #[test]
+    fn cubes_outside_the_grid_are_rejected() {
+        let text = r#"(version: 4, width: 2, depth: 2, height: 2, cubes: [(x: 2, y: 0, z: 0, cube: "Red")])"#;
+        let result = from_str(text, &test_cubemap());
+        assert!(matches!(result, Err(ConstructionError::OutOfGrid { x: 2, y: 0, z: 0 })));
+    }
+}
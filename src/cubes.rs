@@ -1,363 +1,451 @@
-use std::f32::consts::PI;
-
 use egui::epaint::*;
 use enum_map::EnumMap;
 
 use macroquad::models::{Mesh, Vertex};
-use macroquad::prelude::{vec2, vec3, Mat3};
-use macroquad::color::{Color, colors};
+use macroquad::prelude::{vec2, vec3, Mat3, Vec3};
+use macroquad::color::Color;
 
 use egui::{Shape, Stroke};
 
-#[derive(Clone, Copy, Debug, Enum)]
-pub enum Cube {
-    Green,
-    Red,
-    Blue,
-    Yellow,
-    White,
-    NorthRedWhite,
-    WestRedWhite,
-    SouthRedWhite,
-    EastRedWhite,
+use crate::assets;
+use crate::palette::PaletteCube;
+use crate::ply::{self, PlyError, PlyModel, MAX_MESH_VERTICES};
+
+/// A cube as placed in the grid: its kind, identified by the position in the palette,
+/// and how many quarter turns it was spun counterclockwise around the vertical axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cube {
+    kind: usize,
+    quarter_turns: usize,
+}
+
+impl Cube {
+    fn new(kind: usize) -> Self {
+        Cube { kind, quarter_turns: 0 }
+    }
+
+    pub fn quarter_turns(self) -> usize {
+        self.quarter_turns
+    }
+
+    /// The same kind of cube, as it is shown in the palette.
+    pub fn unrotated(self) -> Cube {
+        Cube::new(self.kind)
+    }
+}
+
+/// The six faces of a cube, named after the direction they look at.
+/// Every face has its own "up" direction, used to orient its `FaceColors`:
+/// the vertical faces have +z as up, the top face has -x (like the builder schema
+/// and the top camera) and the bottom face has +x.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum Face {
+    PosX,
+    NegX,
+    PosY,
+    NegY,
+    Top,
+    Bottom,
+}
+
+impl Face {
+    pub fn normal(self) -> Vec3 {
+        match self {
+            Face::PosX => vec3(1.0, 0.0, 0.0),
+            Face::NegX => vec3(-1.0, 0.0, 0.0),
+            Face::PosY => vec3(0.0, 1.0, 0.0),
+            Face::NegY => vec3(0.0, -1.0, 0.0),
+            Face::Top => vec3(0.0, 0.0, 1.0),
+            Face::Bottom => vec3(0.0, 0.0, -1.0),
+        }
+    }
+
+    pub fn up(self) -> Vec3 {
+        match self {
+            Face::Top => vec3(-1.0, 0.0, 0.0),
+            Face::Bottom => vec3(1.0, 0.0, 0.0),
+            _ => vec3(0.0, 0.0, 1.0),
+        }
+    }
+
+    /// Direction pointing to the right for someone looking at the face from outside.
+    pub fn right(self) -> Vec3 {
+        self.up().cross(self.normal())
+    }
+}
+
+/// Colors of a cube face as seen from outside, split in the four triangles that join
+/// the center of the face with its top, right, bottom and left edges (in this order).
+/// A solid face has four equal colors, a diagonally split one has two pairs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FaceColors(pub [Color32; 4]);
+
+impl FaceColors {
+    pub fn solid(color: Color32) -> Self {
+        FaceColors([color; 4])
+    }
+
+    /// Face split by the diagonal that goes from the top-left to the bottom-right corner.
+    pub fn split_main_diagonal(top_right: Color32, bottom_left: Color32) -> Self {
+        FaceColors([top_right, top_right, bottom_left, bottom_left])
+    }
+
+    /// Face split by the diagonal that goes from the bottom-left to the top-right corner.
+    pub fn split_anti_diagonal(top_left: Color32, bottom_right: Color32) -> Self {
+        FaceColors([top_left, bottom_right, bottom_right, top_left])
+    }
+
+    /// Turns the colors of the face counterclockwise, by the given number of quarter turns.
+    fn rotated_ccw(self, quarter_turns: usize) -> Self {
+        let FaceColors(colors) = self;
+        FaceColors([0, 1, 2, 3].map(|i| colors[(i + quarter_turns) % 4]))
+    }
+
+    pub fn is_solid(&self) -> bool {
+        self.0.iter().all(|color| *color == self.0[0])
+    }
+}
+
+/// Full description of how a cube looks like, face by face.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CubeDescription {
+    faces: EnumMap<Face, FaceColors>,
+}
+
+impl CubeDescription {
+    pub fn new(faces: EnumMap<Face, FaceColors>) -> Self {
+        CubeDescription { faces }
+    }
+
+    pub fn solid(color: Color32) -> Self {
+        CubeDescription {
+            faces: enum_map! { _ => FaceColors::solid(color) },
+        }
+    }
+
+    /// Cube cut in two halves by a vertical diagonal plane: the `north` half holds the
+    /// -x and -y faces, the `south` half the +x and +y ones.
+    pub fn split(north: Color32, south: Color32) -> Self {
+        CubeDescription {
+            faces: enum_map! {
+                Face::PosX | Face::PosY => FaceColors::solid(south),
+                Face::NegX | Face::NegY => FaceColors::solid(north),
+                Face::Top => FaceColors::split_anti_diagonal(north, south),
+                Face::Bottom => FaceColors::split_main_diagonal(south, north),
+            },
+        }
+    }
+
+    pub fn face(&self, face: Face) -> FaceColors {
+        self.faces[face]
+    }
+
+    /// Rotates the cube counterclockwise (seen from above) around the vertical axis,
+    /// by the given number of quarter turns.
+    pub fn rotated_z(self, quarter_turns: usize) -> Self {
+        let mut rotated = self;
+        for _ in 0..quarter_turns % 4 {
+            let faces = rotated.faces;
+            // vertical faces keep their up direction, so their colors just move around;
+            // the top and bottom ones spin in place.
+            rotated.faces = enum_map! {
+                Face::PosX => faces[Face::NegY],
+                Face::PosY => faces[Face::PosX],
+                Face::NegX => faces[Face::PosY],
+                Face::NegY => faces[Face::NegX],
+                Face::Top => faces[Face::Top].rotated_ccw(1),
+                Face::Bottom => faces[Face::Bottom].rotated_ccw(3),
+            };
+        }
+
+        rotated
+    }
+
+    /// Applies a rotation or a reflection, given as a matrix that maps axes onto axes.
+    /// Every triangle of a face keeps the direction it points to, so mirrored cubes
+    /// get mirrored faces.
+    pub fn transformed(self, transform: Mat3) -> Self {
+        let directions = |face: Face| [face.up(), face.right(), -face.up(), -face.right()];
+        let same = |a: Vec3, b: Vec3| a.distance(b) < 0.5;
+        CubeDescription {
+            faces: enum_map! {
+                face => {
+                    let (source, source_colors) = self.faces.iter()
+                        .find(|(source, _)| same(transform * source.normal(), face.normal()))
+                        .expect("the transform maps faces onto faces");
+                    let target_directions = directions(face);
+                    let mut colors = source_colors.0;
+                    for (color, direction) in source_colors.0.iter().zip(directions(source)) {
+                        let index = target_directions.iter()
+                            .position(|target| same(transform * direction, *target))
+                            .expect("the transform maps edges onto edges");
+                        colors[index] = *color;
+                    }
+                    FaceColors(colors)
+                }
+            },
+        }
+    }
 }
 
 pub struct CubeInfo {
+    pub name: String,
+    pub description: CubeDescription,
     pub mesh: Mesh,
-    pub egui_shape: egui::Shape,
 }
 
-pub type CubeMap = EnumMap<Cube, CubeInfo>;
-
-pub fn create_default_cubemap() -> CubeMap {
-    let map = enum_map!{
-        Cube::Green => CubeInfo {
-            mesh: default_cube(colors::GREEN),
-            egui_shape: Shape::Rect(RectShape{
-                rect: egui::Rect { min: pos2(0.0, 0.0), max: pos2(1.0, 1.0) },
-                rounding: Rounding::none(),
-                fill: egui::Color32::GREEN,
-                stroke: Stroke::none(),
-            }),
-        },
-        Cube::Red => CubeInfo {
-            mesh: default_cube(colors::RED),
-            egui_shape: Shape::Rect(RectShape{
-                rect: egui::Rect { min: pos2(0.0, 0.0), max: pos2(1.0, 1.0) },
-                rounding: Rounding::none(),
-                fill: egui::Color32::RED,
-                stroke: Stroke::none(),
-            }),
-        },
-        Cube::Blue => CubeInfo {
-            mesh: default_cube(colors::BLUE),
-            egui_shape: Shape::Rect(RectShape{
-                rect: egui::Rect { min: pos2(0.0, 0.0), max: pos2(1.0, 1.0) },
-                rounding: Rounding::none(),
-                fill: egui::Color32::BLUE,
-                stroke: Stroke::none(),
+impl CubeInfo {
+    pub fn new(name: String, description: CubeDescription, model: &PlyModel) -> Self {
+        CubeInfo {
+            name,
+            description,
+            mesh: cube_mesh(model, &description).unwrap_or_else(|err| {
+                macroquad::logging::warn!("could not use {}: {}", CUBE_MODEL_PATH, err);
+                let embedded = ply::parse(EMBEDDED_CUBE_MODEL).expect("the embedded cube model is valid");
+                cube_mesh(&embedded, &description).expect("the embedded cube model is small enough")
             }),
-        },
-        Cube::Yellow => CubeInfo {
-            mesh: default_cube(colors::GOLD),
-            egui_shape: Shape::Rect(RectShape{
-                rect: egui::Rect { min: pos2(0.0, 0.0), max: pos2(1.0, 1.0) },
-                rounding: Rounding::none(),
-                fill: egui::Color32::GOLD,
-                stroke: Stroke::none(),
-            }),
-        },
-        Cube::White => CubeInfo {
-            mesh: default_cube(colors::WHITE),
-            egui_shape: Shape::Rect(RectShape{
-                rect: egui::Rect { min: pos2(0.0, 0.0), max: pos2(1.0, 1.0) },
-                rounding: Rounding::none(),
-                fill: egui::Color32::WHITE,
-                stroke: Stroke::none(),
-            }),
-        },
-        Cube::NorthRedWhite => CubeInfo {
-            mesh: split_color_cube(colors::RED, colors::WHITE, 0.0),
-            egui_shape: Shape::Vec(vec![
-                Shape::Path(PathShape {
-                    points: vec![pos2(1.0, 0.0), pos2(0.0, 0.0), pos2(0.0, 1.0)],
-                    closed: true,
-                    stroke: Stroke::none(),
-                    fill: egui::Color32::RED,
-                }),
-                Shape::Path(PathShape {
-                    points: vec![pos2(1.0, 1.0), pos2(1.0, 0.0), pos2(0.0, 1.0)],
-                    closed: true,
-                    stroke: Stroke::none(),
-                    fill: egui::Color32::WHITE,
-                }),
-            ]),
-        },
-        Cube::WestRedWhite => CubeInfo {
-            mesh: split_color_cube(colors::RED, colors::WHITE, 0.5 * PI),
-            egui_shape: Shape::Vec(vec![
-                Shape::Path(PathShape {
-                    points: vec![pos2(0.0, 0.0), pos2(0.0, 1.0), pos2(1.0, 1.0)],
-                    closed: true,
-                    stroke: Stroke::none(),
-                    fill: egui::Color32::RED,
-                }),
-                Shape::Path(PathShape {
-                    points: vec![pos2(0.0, 0.0), pos2(1.0, 0.0), pos2(1.0, 1.0)],
-                    closed: true,
-                    stroke: Stroke::none(),
-                    fill: egui::Color32::WHITE,
-                }),
-            ]),
-        },
-        Cube::SouthRedWhite => CubeInfo {
-            mesh: split_color_cube(colors::RED, colors::WHITE, 1.0 * PI),
-            egui_shape: Shape::Vec(vec![
-                Shape::Path(PathShape {
-                    points: vec![pos2(1.0, 0.0), pos2(0.0, 0.0), pos2(0.0, 1.0)],
-                    closed: true,
-                    stroke: Stroke::none(),
-                    fill: egui::Color32::WHITE,
-                }),
-                Shape::Path(PathShape {
-                    points: vec![pos2(1.0, 1.0), pos2(1.0, 0.0), pos2(0.0, 1.0)],
-                    closed: true,
-                    stroke: Stroke::none(),
-                    fill: egui::Color32::RED,
-                }),
-            ]),
-        },
-        Cube::EastRedWhite => CubeInfo {
-            mesh: split_color_cube(colors::RED, colors::WHITE, 1.5 * PI),
-            egui_shape: Shape::Vec(vec![
-                Shape::Path(PathShape {
-                    points: vec![pos2(0.0, 0.0), pos2(0.0, 1.0), pos2(1.0, 1.0)],
-                    closed: true,
-                    stroke: Stroke::none(),
-                    fill: egui::Color32::WHITE,
-                }),
-                Shape::Path(PathShape {
-                    points: vec![pos2(0.0, 0.0), pos2(1.0, 0.0), pos2(1.0, 1.0)],
-                    closed: true,
-                    stroke: Stroke::none(),
-                    fill: egui::Color32::RED,
-                }),
-            ]),
-        },
-    };
+        }
+    }
+}
+
+/// Meshes and shapes of every cube in the palette.
+pub struct CubeMap {
+    infos: Vec<CubeInfo>,
+}
+
+impl CubeMap {
+    pub fn iter(&self) -> impl Iterator<Item = (Cube, &CubeInfo)> {
+        self.infos.iter().enumerate().map(|(index, info)| (Cube::new(index), info))
+    }
+
+    pub fn find(&self, name: &str) -> Option<Cube> {
+        self.infos.iter().position(|info| info.name == name).map(Cube::new)
+    }
+
+    /// How the cube looks like, taking its rotation into account.
+    pub fn description(&self, cube: Cube) -> CubeDescription {
+        self[cube].description.rotated_z(cube.quarter_turns)
+    }
+
+    /// Shape of the top face of the cube, as seen in the builder schema.
+    pub fn top_shape(&self, cube: Cube) -> Shape {
+        face_shape(self.description(cube).face(Face::Top))
+    }
+
+    /// Spins the cube counterclockwise by the given number of quarter turns. Rotations that
+    /// leave the cube looking the same are dropped, so equal looking cubes compare equal.
+    pub fn rotated(&self, cube: Cube, quarter_turns: usize) -> Cube {
+        let description = self[cube].description;
+        let turned = description.rotated_z(cube.quarter_turns + quarter_turns);
+        let quarter_turns = (0..4)
+            .find(|&turns| description.rotated_z(turns) == turned)
+            .expect("a rotation of the cube looks like the cube itself");
+
+        Cube { kind: cube.kind, quarter_turns }
+    }
+
+    /// The cube that looks like the given one once transformed, e.g. the split cube turned
+    /// the other way once mirrored. None when no kind of the palette matches in any rotation,
+    /// e.g. a cube with a colored top laid on its side.
+    pub fn transformed(&self, cube: Cube, transform: Mat3) -> Option<Cube> {
+        let description = self.description(cube).transformed(transform);
+        self.infos.iter()
+            .enumerate()
+            .find_map(|(kind, info)| {
+                (0..4)
+                    .find(|&turns| info.description.rotated_z(turns) == description)
+                    .map(|turns| Cube { kind, quarter_turns: turns })
+            })
+    }
+}
+
+impl std::ops::Index<Cube> for CubeMap {
+    type Output = CubeInfo;
+
+    fn index(&self, cube: Cube) -> &CubeInfo {
+        &self.infos[cube.kind]
+    }
+}
+
+/// Model of the bevelled cube. It is read at startup, so that it can be restyled without rebuilding.
+pub const CUBE_MODEL_PATH: &str = "models/basic_cube.ply";
+const EMBEDDED_CUBE_MODEL: &[u8] = include_bytes!("../models/basic_cube.ply");
+
+pub async fn load_cube_model() -> PlyModel {
+    assets::load_or_embedded(CUBE_MODEL_PATH, EMBEDDED_CUBE_MODEL, ply::parse).await
+}
+
+pub fn read_cube_model() -> PlyModel {
+    assets::read_or_embedded(CUBE_MODEL_PATH, EMBEDDED_CUBE_MODEL, ply::parse)
+}
+
+pub fn create_cubemap(palette: Vec<PaletteCube>, model: &PlyModel) -> CubeMap {
+    let infos = palette.into_iter()
+        .map(|cube| CubeInfo::new(cube.name, cube.description, model))
+        .collect();
 
-    map
+    CubeMap { infos }
 }
 
-pub fn default_cube(color: Color) -> Mesh {
-    split_color_cube(color, color, 0.0)
+/// The cubes of the default palette, kept here so that the tests do not change with the palette
+/// a school configured.
+#[cfg(test)]
+const TEST_PALETTE: &str = r#"(
+    cubes: [
+        (name: "Green", pattern: Solid((0, 228, 48))),
+        (name: "Red", pattern: Solid((230, 41, 55))),
+        (name: "Blue", pattern: Solid((0, 121, 241))),
+        (name: "Yellow", pattern: Solid((255, 203, 0))),
+        (name: "White", pattern: Solid((255, 255, 255))),
+        (name: "RedWhite", pattern: Split(north: (230, 41, 55), south: (255, 255, 255))),
+        (name: "BlueRedTop", pattern: Faces(
+            pos_x: Solid((0, 121, 241)),
+            neg_x: Solid((0, 121, 241)),
+            pos_y: Solid((0, 121, 241)),
+            neg_y: Solid((0, 121, 241)),
+            top: Solid((230, 41, 55)),
+            bottom: Solid((0, 121, 241)),
+        )),
+    ],
+)"#;
+
+#[cfg(test)]
+pub fn test_cubemap() -> CubeMap {
+    let palette = crate::palette::parse(TEST_PALETTE).expect("the test palette is valid");
+    let model = ply::parse(EMBEDDED_CUBE_MODEL).expect("the embedded cube model is valid");
+    create_cubemap(palette, &model)
+}
+
+/// Builds the 2D shape of a face in the unit square, merging adjacent triangles of the same color.
+pub fn face_shape(face_colors: FaceColors) -> Shape {
+    let FaceColors(colors) = face_colors;
+    if face_colors.is_solid() {
+        return Shape::Rect(RectShape {
+            rect: egui::Rect { min: pos2(0.0, 0.0), max: pos2(1.0, 1.0) },
+            rounding: Rounding::none(),
+            fill: colors[0],
+            stroke: Stroke::none(),
+        });
+    }
+
+    let corners = [pos2(0.0, 0.0), pos2(1.0, 0.0), pos2(1.0, 1.0), pos2(0.0, 1.0)];
+    let center = pos2(0.5, 0.5);
+    // start from a triangle that has a different color than the previous one,
+    // so that no group of same colored triangles wraps around
+    let start = (0..4).find(|&i| colors[i] != colors[(i + 3) % 4]).unwrap_or(0);
+    let mut shapes = Vec::new();
+    let mut points = vec![corners[start]];
+    for step in 0..4 {
+        let i = (start + step) % 4;
+        let next = (i + 1) % 4;
+        points.push(corners[next]);
+        if step == 3 || colors[next] != colors[i] {
+            points.push(center);
+            shapes.push(Shape::Path(PathShape {
+                points,
+                closed: true,
+                stroke: Stroke::none(),
+                fill: colors[i],
+            }));
+            points = vec![corners[next]];
+        }
+    }
+
+    Shape::Vec(shapes)
 }
 
-pub fn split_color_cube(color_north: Color, color_south: Color, rotation: f32) -> Mesh {
+/// Vertices darker than this are part of the bevel, and keep the color they have in the model.
+/// All the other ones are painted with the colors of the cube description.
+const BEVEL_THRESHOLD: u8 = 128;
 
+/// Builds the mesh of a cube by painting the model: every colored triangle is cut along the
+/// diagonals of the face it lies on, and each piece gets the color of its part of the face.
+/// Fails when the mesh would have too many vertices, since cutting the triangles adds some.
+pub fn cube_mesh(model: &PlyModel, description: &CubeDescription) -> Result<Mesh, PlyError> {
     let uv = vec2(0.0, 0.0);
-    let rot = Mat3::from_rotation_z(rotation);
-    let vertices = vec![
-        Vertex{position: rot*vec3(-0.500000, -0.466667,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667, -0.500000,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667, -0.466667,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000, -0.500000,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667, -0.500000,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000,  0.466667,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000,  0.500000,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000,  0.466667,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000, -0.466667, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667, -0.500000, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667, -0.466667, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000, -0.466667,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000, -0.500000,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000, -0.466667,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667,  0.500000,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000,  0.500000,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667,  0.500000,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000,  0.500000,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000,  0.466667,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000,  0.466667,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000,  0.500000, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667,  0.466667, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667,  0.500000, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000,  0.466667, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000, -0.500000,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000, -0.466667,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000,  0.500000,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667,  0.466667,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667,  0.500000,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667,  0.466667,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667,  0.466667, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000, -0.466667, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000,  0.466667, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667,  0.500000, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000,  0.500000, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667,  0.500000,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667, -0.466667, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000, -0.500000, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667, -0.500000, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000, -0.500000,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667, -0.500000,  0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667, -0.500000,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667, -0.466667,  0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667, -0.500000, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000, -0.500000, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000, -0.500000, -0.500000), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667,  0.500000, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000,  0.500000, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000, -0.466667, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000, -0.500000, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000,  0.466667, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000,  0.500000, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.466667,  0.500000, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3( 0.500000, -0.466667, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.500000,  0.466667, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667, -0.500000, -0.466667), uv, color: colors::BLACK},
-        Vertex{position: rot*vec3(-0.466667,  0.466667,  0.500000), uv, color: color_south},
-        Vertex{position: rot*vec3( 0.466667, -0.466667,  0.500000), uv, color: color_south},
-        Vertex{position: rot*vec3( 0.466667,  0.466667,  0.500000), uv, color: color_south},
-        Vertex{position: rot*vec3( 0.466667, -0.500000,  0.466667), uv, color: color_north},
-        Vertex{position: rot*vec3(-0.466667, -0.500000, -0.466667), uv, color: color_north},
-        Vertex{position: rot*vec3( 0.466667, -0.500000, -0.466667), uv, color: color_north},
-        Vertex{position: rot*vec3(-0.466667,  0.500000,  0.466667), uv, color: color_south},
-        Vertex{position: rot*vec3( 0.466667,  0.500000, -0.466667), uv, color: color_south},
-        Vertex{position: rot*vec3(-0.466667,  0.500000, -0.466667), uv, color: color_south},
-        Vertex{position: rot*vec3(-0.500000, -0.466667,  0.466667), uv, color: color_north},
-        Vertex{position: rot*vec3(-0.500000,  0.466667, -0.466667), uv, color: color_north},
-        Vertex{position: rot*vec3(-0.500000, -0.466667, -0.466667), uv, color: color_north},
-        Vertex{position: rot*vec3( 0.500000,  0.466667,  0.466667), uv, color: color_south},
-        Vertex{position: rot*vec3( 0.500000, -0.466667, -0.466667), uv, color: color_south},
-        Vertex{position: rot*vec3( 0.500000,  0.466667, -0.466667), uv, color: color_south},
-        Vertex{position: rot*vec3(-0.466667,  0.466667, -0.500000), uv, color: color_south},
-        Vertex{position: rot*vec3( 0.466667,  0.466667, -0.500000), uv, color: color_south},
-        Vertex{position: rot*vec3( 0.466667, -0.466667, -0.500000), uv, color: color_south},
-        Vertex{position: rot*vec3(-0.466667, -0.500000,  0.466667), uv, color: color_north},
-        Vertex{position: rot*vec3( 0.466667,  0.500000,  0.466667), uv, color: color_south},
-        Vertex{position: rot*vec3(-0.500000,  0.466667,  0.466667), uv, color: color_north},
-        Vertex{position: rot*vec3( 0.500000, -0.466667,  0.466667), uv, color: color_south},
-        Vertex{position: rot*vec3(-0.466667,  0.466667, -0.500000), uv, color: color_north},
-        Vertex{position: rot*vec3( 0.466667, -0.466667, -0.500000), uv, color: color_north},
-        Vertex{position: rot*vec3(-0.466667, -0.466667, -0.500000), uv, color: color_north},
-        Vertex{position: rot*vec3(-0.466667,  0.466667,  0.500000), uv, color: color_north},
-        Vertex{position: rot*vec3(-0.466667, -0.466667,  0.500000), uv, color: color_north},
-        Vertex{position: rot*vec3( 0.466667, -0.466667,  0.500000), uv, color: color_north},
-    ];
-
-    let indices: Vec<u16> = vec![
-        0,1,2,
-        1,3,4,
-        5,6,7,
-        8,9,10,
-        11,12,13,
-        14,15,16,
-        17,18,15,
-        19,13,18,
-        20,21,22,
-        23,10,21,
-        24,25,3,
-        0,7,25,
-        26,27,28,
-        5,2,27,
-        29,11,19,
-        14,19,17,
-        28,29,14,
-        30,31,32,
-        33,32,34,
-        22,30,33,
-        26,35,6,
-        28,16,35,
-        36,37,31,
-        10,38,36,
-        39,40,12,
-        41,4,40,
-        42,39,11,
-        2,41,42,
-        43,38,9,
-        44,9,45,
-        12,43,44,
-        46,22,33,
-        47,33,34,
-        6,46,47,
-        48,32,31,
-        49,31,37,
-        3,48,49,
-        50,8,23,
-        51,23,20,
-        15,50,51,
-        52,20,22,
-        16,51,52,
-        53,45,8,
-        13,44,53,
-        54,34,32,
-        7,47,54,
-        55,37,38,
-        4,49,55,
-        56,57,58,
-        59,60,61,
-        62,63,64,
-        65,66,67,
-        68,69,70,
-        0,24,1,
-        1,24,3,
-        5,26,6,
-        8,45,9,
-        11,39,12,
-        14,17,15,
-        17,19,18,
-        19,11,13,
-        20,23,21,
-        23,8,10,
-        24,0,25,
-        0,5,7,
-        26,5,27,
-        5,0,2,
-        29,42,11,
-        14,29,19,
-        28,27,29,
-        30,36,31,
-        33,30,32,
-        22,21,30,
-        26,28,35,
-        28,14,16,
-        36,38,37,
-        10,9,38,
-        39,41,40,
-        41,1,4,
-        42,41,39,
-        2,1,41,
-        43,55,38,
-        44,43,9,
-        12,40,43,
-        46,52,22,
-        47,46,33,
-        6,35,46,
-        48,54,32,
-        49,48,31,
-        3,25,48,
-        50,53,8,
-        51,50,23,
-        15,18,50,
-        52,51,20,
-        16,15,51,
-        53,44,45,
-        13,12,44,
-        54,47,34,
-        7,6,47,
-        55,49,37,
-        4,3,49,
-        71,72,73,
-        59,74,60,
-        62,75,63,
-        65,76,66,
-        68,77,69,
-        78,79,80,
-        81,82,83,
-    ];
-
-    Mesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut add_polygon = |points: &[Vec3], color: Color| {
+        if vertices.len() + points.len() > MAX_MESH_VERTICES {
+            return Err(PlyError::TooManyVertices);
+        }
+        let first = vertices.len() as u16;
+        vertices.extend(points.iter().map(|&position| Vertex { position, uv, color }));
+        for i in 1..points.len().saturating_sub(1) as u16 {
+            indices.extend([first, first + i, first + i + 1]);
+        }
+        Ok(())
+    };
+
+    for triangle in model.triangles.iter() {
+        let corners = triangle.map(|index| model.vertices[index]);
+        let positions = corners.map(|vertex| vertex.position);
+        let is_bevel = corners.iter()
+            .any(|vertex| vertex.color[..3].iter().all(|channel| *channel < BEVEL_THRESHOLD));
+        if is_bevel {
+            let [r, g, b, a] = corners[0].color;
+            add_polygon(&positions, Color::from_rgba(r, g, b, a))?;
+            continue;
+        }
+
+        let face = face_of(positions);
+        let FaceColors(face_colors) = description.face(face);
+        // the two diagonals of the face divide it in the top, right, bottom and left parts
+        let (up, right) = (face.up(), face.right());
+        let half_planes = [
+            [up - right, up + right],
+            [right - up, right + up],
+            [-up + right, -up - right],
+            [-right - up, -right + up],
+        ];
+        for (planes, color) in half_planes.iter().zip(face_colors) {
+            let piece = planes.iter()
+                .fold(positions.to_vec(), |polygon, plane| clip_polygon(&polygon, *plane));
+            if piece.len() >= 3 {
+                add_polygon(&piece, Color::from_rgba(color.r(), color.g(), color.b(), color.a()))?;
+            }
+        }
+    }
+
+    Ok(Mesh {
         vertices,
         indices,
         texture: None
+    })
+}
+
+/// The cube face a triangle belongs to, chosen by the side of the cube its center lies on.
+/// Unlike the normal, the position does not depend on the winding of the triangle, which
+/// is easily flipped while editing the model.
+fn face_of(positions: [Vec3; 3]) -> Face {
+    let center = (positions[0] + positions[1] + positions[2]) / 3.0;
+    let abs = center.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+        if center.x > 0.0 { Face::PosX } else { Face::NegX }
+    } else if abs.y >= abs.z {
+        if center.y > 0.0 { Face::PosY } else { Face::NegY }
+    } else if center.z > 0.0 {
+        Face::Top
+    } else {
+        Face::Bottom
+    }
+}
+
+/// Keeps the part of a convex polygon that lies on the positive side of the plane
+/// going through the cube center with the given normal.
+fn clip_polygon(polygon: &[Vec3], plane: Vec3) -> Vec<Vec3> {
+    let mut clipped = Vec::new();
+    for (i, &current) in polygon.iter().enumerate() {
+        let next = polygon[(i + 1) % polygon.len()];
+        let (current_distance, next_distance) = (current.dot(plane), next.dot(plane));
+        if current_distance >= 0.0 {
+            clipped.push(current);
+        }
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            clipped.push(current.lerp(next, t));
+        }
     }
+
+    clipped
 }
@@ -0,0 +1,204 @@
+use serde::Deserialize;
+
+use crate::cubes::{Cube, CubeMap};
+use crate::grid::{CubeGrid, GridTransform};
+
+/// How a construction may be moved and still count as the same one. It can always be moved
+/// around on the paving, but not lifted from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Equivalence {
+    /// Also turned around the vertical axis
+    Rotation,
+    /// Also turned around the vertical axis or seen in a mirror
+    RotationOrMirroring,
+}
+
+/// A cell where the construction of the student does not match the target. The cell may be
+/// outside the student grid, when the target does not fit in it: then `found` is None.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellDifference {
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
+    pub expected: Option<Cube>,
+    pub found: Option<Cube>,
+}
+
+pub struct Comparison {
+    pub equivalent: bool,
+    /// Cells to change to get the target, placed where it matches best in the student grid.
+    /// Empty only when the constructions are equivalent.
+    pub differences: Vec<CellDifference>,
+}
+
+pub fn compare(student: &CubeGrid, target: &CubeGrid, cubemap: &CubeMap, equivalence: Equivalence) -> Comparison {
+    let orientations: Vec<CubeGrid> = orientations(target, cubemap, equivalence).iter()
+        .map(moved_to_origin)
+        .collect();
+    let equivalent = orientations.contains(&moved_to_origin(student));
+
+    // every orientation of the target is tried in every position where it fits in the student grid
+    let mut best: Option<Vec<CellDifference>> = None;
+    for orientation in orientations.iter() {
+        if orientation.width() > student.width() || orientation.depth() > student.depth() {
+            continue;
+        }
+        for dx in 0..=student.width() - orientation.width() {
+            for dy in 0..=student.depth() - orientation.depth() {
+                let differences = differences(student, orientation, dx, dy);
+                if best.as_ref().is_none_or(|best| differences.len() < best.len()) {
+                    best = Some(differences);
+                }
+            }
+        }
+    }
+    // the target does not fit in the student grid, so it is compared where it is
+    let differences = best.unwrap_or_else(|| differences(student, target, 0, 0));
+
+    Comparison { equivalent, differences }
+}
+
+/// The target turned in every way allowed, starting from the target itself.
+fn orientations(target: &CubeGrid, cubemap: &CubeMap, equivalence: Equivalence) -> Vec<CubeGrid> {
+    let transformed = |grid: &CubeGrid, transform: GridTransform| {
+        let matrix = transform.matrix();
+        grid.transformed(transform, |cube| cubemap.transformed(cube, matrix))
+    };
+    let mut mirrors = vec![target.clone()];
+    // a palette may have cubes whose mirror image is not in it, then the target is only turned
+    if equivalence == Equivalence::RotationOrMirroring {
+        mirrors.extend(transformed(target, GridTransform::MirrorX));
+    }
+
+    let mut orientations = Vec::with_capacity(4 * mirrors.len());
+    for mirror in mirrors {
+        let mut rotated = mirror;
+        for _ in 0..4 {
+            let next = transformed(&rotated, GridTransform::RotateZ)
+                .expect("every cube can be turned around the vertical axis");
+            orientations.push(rotated);
+            rotated = next;
+        }
+    }
+
+    orientations
+}
+
+/// The construction moved to the corner of the paving, in a grid just big enough for it.
+/// The cubes keep their height, since the construction rests on the paving.
+fn moved_to_origin(grid: &CubeGrid) -> CubeGrid {
+    let cubes: Vec<_> = grid.iter_cubes().collect();
+    let min_x = cubes.iter().map(|&(x, _, _, _)| x).min().unwrap_or(0);
+    let min_y = cubes.iter().map(|&(_, y, _, _)| y).min().unwrap_or(0);
+    let width = cubes.iter().map(|&(x, _, _, _)| x + 1 - min_x).max().unwrap_or(0);
+    let depth = cubes.iter().map(|&(_, y, _, _)| y + 1 - min_y).max().unwrap_or(0);
+    let height = cubes.iter().map(|&(_, _, z, _)| z + 1).max().unwrap_or(0);
+
+    let mut moved = CubeGrid::new(width, depth, height);
+    for (x, y, z, cube) in cubes {
+        moved.set(x - min_x, y - min_y, z, Some(cube));
+    }
+
+    moved
+}
+
+/// Compares every cell of the student grid with the target moved by (dx, dy).
+/// Target cubes that end up outside the student grid are reported as missing.
+fn differences(student: &CubeGrid, target: &CubeGrid, dx: usize, dy: usize) -> Vec<CellDifference> {
+    let mut differences = Vec::new();
+    for x in 0..student.width() {
+        for y in 0..student.depth() {
+            for z in 0..student.height() {
+                let expected = match (x.checked_sub(dx), y.checked_sub(dy)) {
+                    (Some(tx), Some(ty)) if target.contains(tx, ty, z) => target.get(tx, ty, z),
+                    _ => None,
+                };
+                let found = student.get(x, y, z);
+                if expected != found {
+                    differences.push(CellDifference { x, y, z, expected, found });
+                }
+            }
+        }
+    }
+    for (tx, ty, z, cube) in target.iter_cubes() {
+        let (x, y) = (tx + dx, ty + dy);
+        if !student.contains(x, y, z) {
+            differences.push(CellDifference { x, y, z, expected: Some(cube), found: None });
+        }
+    }
+
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubes::test_cubemap;
+
+    /// Grid of the given size with the named cubes, unrotated.
+    fn grid(cubemap: &CubeMap, (width, depth, height): (usize, usize, usize), cubes: &[(usize, usize, usize, &str)]) -> CubeGrid {
+        let mut grid = CubeGrid::new(width, depth, height);
+        for &(x, y, z, name) in cubes {
+            grid.set(x, y, z, cubemap.find(name));
+        }
+        grid
+    }
+
+    /// An L whose arms have different colors, so that its mirror image cannot be turned into it.
+    fn target(cubemap: &CubeMap) -> CubeGrid {
+        grid(cubemap, (3, 3, 2), &[(0, 0, 0, "Red"), (1, 0, 0, "Blue"), (0, 1, 0, "Green")])
+    }
+
+    #[test]
+    fn moved_construction_is_equivalent() {
+        let cubemap = test_cubemap();
+        let student = grid(&cubemap, (3, 3, 2), &[(1, 1, 0, "Red"), (2, 1, 0, "Blue"), (1, 2, 0, "Green")]);
+
+        let comparison = compare(&student, &target(&cubemap), &cubemap, Equivalence::Rotation);
+        assert!(comparison.equivalent);
+        assert!(comparison.differences.is_empty());
+    }
+
+    #[test]
+    fn turned_construction_is_equivalent() {
+        let cubemap = test_cubemap();
+        let student = grid(&cubemap, (3, 3, 2), &[(1, 0, 0, "Red"), (1, 1, 0, "Blue"), (0, 0, 0, "Green")]);
+
+        let comparison = compare(&student, &target(&cubemap), &cubemap, Equivalence::Rotation);
+        assert!(comparison.equivalent);
+        assert!(comparison.differences.is_empty());
+    }
+
+    #[test]
+    fn mirrored_construction_needs_mirroring() {
+        let cubemap = test_cubemap();
+        let student = grid(&cubemap, (3, 3, 2), &[(1, 0, 0, "Red"), (0, 0, 0, "Blue"), (1, 1, 0, "Green")]);
+        let target = target(&cubemap);
+
+        assert!(!compare(&student, &target, &cubemap, Equivalence::Rotation).equivalent);
+        assert!(compare(&student, &target, &cubemap, Equivalence::RotationOrMirroring).equivalent);
+    }
+
+    #[test]
+    fn wrong_cube_is_the_only_difference() {
+        let cubemap = test_cubemap();
+        let student = grid(&cubemap, (3, 3, 2), &[(0, 0, 0, "Red"), (1, 0, 0, "Yellow"), (0, 1, 0, "Green")]);
+
+        let comparison = compare(&student, &target(&cubemap), &cubemap, Equivalence::Rotation);
+        assert!(!comparison.equivalent);
+        let expected = CellDifference { x: 1, y: 0, z: 0, expected: cubemap.find("Blue"), found: cubemap.find("Yellow") };
+        assert_eq!(comparison.differences, vec![expected]);
+    }
+
+    #[test]
+    fn target_cubes_outside_the_student_grid_are_missing() {
+        let cubemap = test_cubemap();
+        let target = grid(&cubemap, (2, 1, 2), &[(0, 0, 0, "Red"), (0, 0, 1, "Blue")]);
+        let student = grid(&cubemap, (2, 1, 1), &[(0, 0, 0, "Red")]);
+
+        let comparison = compare(&student, &target, &cubemap, Equivalence::Rotation);
+        assert!(!comparison.equivalent);
+        let missing = CellDifference { x: 0, y: 0, z: 1, expected: cubemap.find("Blue"), found: None };
+        assert_eq!(comparison.differences, vec![missing]);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::cubes::{Cube, CubeMap};
+use crate::equivalence::{self, Comparison, Equivalence};
+use crate::grid::CubeGrid;
+use crate::pack::Level;
+use crate::projection::{Projection, ProjectionView};
+
+/// A construction to be rebuilt by looking only at its projections.
+/// Any construction that has the same projections is a valid solution, and some exercises
+/// also accept the target moved around the paving or turned.
+pub struct Exercise {
+    width: usize,
+    depth: usize,
+    height: usize,
+    projections: Vec<(ProjectionView, Projection)>,
+    /// Cubes of the palette that can be used, or None for the whole palette
+    cubes: Option<Vec<Cube>>,
+    /// Number of cubes of the listed kinds the student has, or None when there is no limit
+    inventory: Option<Vec<(Cube, usize)>>,
+    hide_schema: bool,
+    /// The target is kept only by the exercises that accept it moved
+    moved_target: Option<(CubeGrid, Equivalence)>,
+}
+
+/// Outcome of checking the construction of the student.
+pub struct Check {
+    /// Views in which the construction differs from the target
+    pub wrong_views: Vec<ProjectionView>,
+    /// Comparison with the target, for the exercises that accept it moved
+    pub comparison: Option<Comparison>,
+}
+
+impl Check {
+    pub fn is_solved(&self) -> bool {
+        self.wrong_views.is_empty() || self.comparison.as_ref().is_some_and(|comparison| comparison.equivalent)
+    }
+}
+
+impl Exercise {
+    /// Shows every view of the target, and lets the student use the whole palette.
+    pub fn new(target: &CubeGrid, cubemap: &CubeMap) -> Self {
+        Exercise::with_views(target, cubemap, &ProjectionView::ALL)
+    }
+
+    /// Also accepts the target moved as allowed by `equivalence`.
+    pub fn with_equivalence(target: &CubeGrid, cubemap: &CubeMap, equivalence: Equivalence) -> Self {
+        Exercise {
+            moved_target: Some((target.clone(), equivalence)),
+            ..Exercise::new(target, cubemap)
+        }
+    }
+
+    pub fn from_level(level: &Level, cubemap: &CubeMap) -> Self {
+        Exercise {
+            cubes: level.cubes.clone(),
+            inventory: level.inventory.clone(),
+            hide_schema: level.hide_schema,
+            moved_target: level.equivalence.map(|equivalence| (level.target.clone(), equivalence)),
+            ..Exercise::with_views(&level.target, cubemap, &level.views)
+        }
+    }
+
+    fn with_views(target: &CubeGrid, cubemap: &CubeMap, views: &[ProjectionView]) -> Self {
+        let projections = views.iter()
+            .map(|&view| (view, Projection::compute(target, cubemap, view)))
+            .collect();
+        Exercise {
+            width: target.width(),
+            depth: target.depth(),
+            height: target.height(),
+            projections,
+            cubes: None,
+            inventory: None,
+            hide_schema: false,
+            moved_target: None,
+        }
+    }
+
+    /// The grid the student starts working on.
+    pub fn empty_grid(&self) -> CubeGrid {
+        CubeGrid::new(self.width, self.depth, self.height)
+    }
+
+    pub fn projections(&self) -> &[(ProjectionView, Projection)] {
+        &self.projections
+    }
+
+    /// Whether the cube of the palette can be used to solve the exercise.
+    pub fn allows(&self, cube: Cube) -> bool {
+        self.cubes.as_ref().is_none_or(|cubes| cubes.contains(&cube))
+    }
+
+    /// How many more cubes of the same kind as the given one can be placed, or None when
+    /// there is no limit. The cubes in the grid are counted, so a removed cube is available again.
+    pub fn remaining(&self, cube: Cube, grid: &CubeGrid) -> Option<usize> {
+        let kind = cube.unrotated();
+        let &(_, count) = self.inventory.as_ref()?.iter().find(|(listed, _)| *listed == kind)?;
+        let used = grid.iter_cubes().filter(|(_, _, _, placed)| placed.unrotated() == kind).count();
+
+        Some(count.saturating_sub(used))
+    }
+
+    /// Whether one more cube like the given one can be placed in the grid.
+    pub fn can_use(&self, cube: Cube, grid: &CubeGrid) -> bool {
+        self.allows(cube.unrotated()) && self.remaining(cube, grid) != Some(0)
+    }
+
+    /// Whether the student must build without looking at the builder schema.
+    pub fn hides_schema(&self) -> bool {
+        self.hide_schema
+    }
+
+    pub fn check(&self, grid: &CubeGrid, cubemap: &CubeMap) -> Check {
+        let wrong_views = self.projections.iter()
+            .filter(|(view, projection)| Projection::compute(grid, cubemap, *view) != *projection)
+            .map(|(view, _)| *view)
+            .collect();
+        let comparison = self.moved_target.as_ref()
+            .map(|(target, equivalence)| equivalence::compare(grid, target, cubemap, *equivalence));
+
+        Check { wrong_views, comparison }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubes::test_cubemap;
+    use crate::pack::load_pack;
+
+    #[test]
+    fn inventory_counts_the_cubes_in_the_grid() {
+        let cubemap = test_cubemap();
+        let pack = load_pack("packs/primi_passi.ron", &cubemap).unwrap();
+        let exercise = Exercise::from_level(&pack.levels[0], &cubemap);
+        let find = |name| cubemap.find(name).unwrap();
+        let (red, blue, green) = (find("Red"), find("Blue"), find("Green"));
+
+        let mut grid = exercise.empty_grid();
+        assert_eq!(exercise.remaining(red, &grid), Some(2));
+        assert!(exercise.can_use(red, &grid));
+        assert!(!exercise.allows(green));
+        assert!(!exercise.can_use(green, &grid));
+
+        grid.set(0, 0, 0, Some(blue));
+        grid.set(1, 0, 0, Some(red));
+        assert_eq!(exercise.remaining(blue, &grid), Some(0));
+        assert!(!exercise.can_use(blue, &grid));
+        assert!(exercise.can_use(red, &grid));
+
+        // a removed cube can be placed again
+        grid.set(0, 0, 0, None);
+        assert!(exercise.can_use(blue, &grid));
+    }
+
+    #[test]
+    fn turned_cubes_count_as_their_kind() {
+        let cubemap = test_cubemap();
+        let red_white = cubemap.find("RedWhite").unwrap();
+        let level = Level {
+            title: String::new(),
+            target: CubeGrid::new(2, 2, 1),
+            cubes: None,
+            inventory: Some(vec![(red_white, 1)]),
+            views: ProjectionView::ALL.to_vec(),
+            hide_schema: false,
+            equivalence: None,
+        };
+        let exercise = Exercise::from_level(&level, &cubemap);
+        let turned = cubemap.rotated(red_white, 1);
+
+        let mut grid = exercise.empty_grid();
+        assert!(exercise.can_use(turned, &grid));
+        grid.set(0, 0, 0, Some(turned));
+        assert_eq!(exercise.remaining(red_white, &grid), Some(0));
+        assert!(!exercise.can_use(cubemap.rotated(red_white, 2), &grid));
+    }
+
+    #[test]
+    fn cubes_without_inventory_have_no_limit() {
+        let cubemap = test_cubemap();
+        let mut target = CubeGrid::new(2, 2, 2);
+        target.set(0, 0, 0, cubemap.find("Yellow"));
+        let exercise = Exercise::new(&target, &cubemap);
+
+        let mut grid = exercise.empty_grid();
+        for (cube, _) in cubemap.iter() {
+            assert!(exercise.can_use(cube, &grid));
+            assert_eq!(exercise.remaining(cube, &grid), None);
+        }
+        grid.set(0, 0, 0, cubemap.find("Yellow"));
+        assert!(exercise.check(&grid, &cubemap).is_solved());
+    }
+}
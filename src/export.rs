@@ -0,0 +1,109 @@
+use std::fmt;
+use std::path::Path;
+
+use macroquad::miniquad;
+use macroquad::prelude::*;
+
+use crate::i18n::{tr_in, Language};
+
+/// Biggest side of an exported picture, in pixels
+pub const MAX_EXPORT_SIZE: u32 = 4096;
+
+/// How the pictures are exported.
+#[derive(Clone, Copy, Debug)]
+pub struct ExportOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Leave the background transparent instead of white, to paste the picture over a worksheet
+    pub transparent: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            width: 1024,
+            height: 768,
+            transparent: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    InvalidSize,
+    Image(image::ImageError),
+}
+
+impl ExportError {
+    /// Like `ConstructionError::message`.
+    pub fn message(&self, language: Language) -> String {
+        let tr = |key| tr_in(language, key);
+        match self {
+            ExportError::InvalidSize => tr("error.export_size").replace("{max}", &MAX_EXPORT_SIZE.to_string()),
+            ExportError::Image(err) => tr("error.image").replace("{error}", &err.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Language::English))
+    }
+}
+
+impl From<image::ImageError> for ExportError {
+    fn from(err: image::ImageError) -> Self {
+        ExportError::Image(err)
+    }
+}
+
+/// Renders the scene offscreen through the camera, without the user interface,
+/// and saves it as a PNG file. `draw_scene` is called with the camera already set.
+pub fn export_png(path: impl AsRef<Path>, camera: &Camera3D, options: ExportOptions, draw_scene: impl FnOnce()) -> Result<(), ExportError> {
+    let ExportOptions { width, height, transparent } = options;
+    let valid_size = 1..=MAX_EXPORT_SIZE;
+    if !valid_size.contains(&width) || !valid_size.contains(&height) {
+        return Err(ExportError::InvalidSize);
+    }
+
+    // macroquad render targets have no depth buffer, which the cubes need to hide each other
+    let context = unsafe { get_internal_gl() }.quad_context;
+    let color_texture = miniquad::Texture::new_render_texture(context, miniquad::TextureParams {
+        width,
+        height,
+        ..Default::default()
+    });
+    let depth_texture = miniquad::Texture::new_render_texture(context, miniquad::TextureParams {
+        format: miniquad::TextureFormat::Depth,
+        width,
+        height,
+        ..Default::default()
+    });
+    let render_target = RenderTarget {
+        texture: Texture2D::from_miniquad_texture(color_texture),
+        render_pass: miniquad::RenderPass::new(context, color_texture, Some(depth_texture)),
+    };
+
+    let export_camera = Camera3D {
+        render_target: Some(render_target),
+        viewport: None,
+        aspect: Some(width as f32 / height as f32),
+        ..*camera
+    };
+    set_camera(&export_camera);
+    clear_background(if transparent { Color::new(1.0, 1.0, 1.0, 0.0) } else { WHITE });
+    draw_scene();
+    // switching camera sends the pending draw calls to the render target
+    set_default_camera();
+
+    let picture = render_target.texture.get_texture_data();
+    render_target.delete();
+    depth_texture.delete();
+
+    // OpenGL stores the rows from the bottom up
+    let row_size = width as usize * 4;
+    let bytes: Vec<u8> = picture.bytes.chunks(row_size).rev().flatten().copied().collect();
+    image::save_buffer(path, &bytes, width, height, image::ColorType::Rgba8)?;
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use macroquad::prelude::{vec3, Mat3, Vec3};
+
+use crate::cubes::Cube;
+
+pub const DEFAULT_GRID_SIZE: usize = 4;
+pub const MAX_GRID_SIZE: usize = 8;
+
+/// Quarter turns and reflections of the whole grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridTransform {
+    /// Counterclockwise around the x axis, seen from +x: +y goes up.
+    RotateX,
+    /// Counterclockwise around the y axis, seen from +y: up goes to +x.
+    RotateY,
+    /// Counterclockwise around the vertical axis, seen from above: +x goes to +y.
+    RotateZ,
+    /// Swaps the first and the last row of the builder.
+    MirrorX,
+    /// Swaps the first and the last column of the builder.
+    MirrorY,
+}
+
+impl GridTransform {
+    /// The transform as a matrix acting on directions.
+    pub fn matrix(self) -> Mat3 {
+        match self {
+            GridTransform::RotateX => Mat3::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            GridTransform::RotateY => Mat3::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            GridTransform::RotateZ => Mat3::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            GridTransform::MirrorX => Mat3::from_diagonal(vec3(-1.0, 1.0, 1.0)),
+            GridTransform::MirrorY => Mat3::from_diagonal(vec3(1.0, -1.0, 1.0)),
+        }
+    }
+}
+
+/// Three-dimensional grid of cubes. `x` runs along the rows of the builder,
+/// `y` along its columns and `z` is the height above the paving.
+/// The sizes along each axis are respectively the width, depth and height.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeGrid {
+    width: usize,
+    depth: usize,
+    height: usize,
+    // cells of a column are contiguous, so that they can be borrowed as a slice
+    cells: Vec<Option<Cube>>,
+}
+
+impl Default for CubeGrid {
+    fn default() -> Self {
+        CubeGrid::new(DEFAULT_GRID_SIZE, DEFAULT_GRID_SIZE, DEFAULT_GRID_SIZE)
+    }
+}
+
+impl CubeGrid {
+    pub fn new(width: usize, depth: usize, height: usize) -> Self {
+        CubeGrid {
+            width,
+            depth,
+            height,
+            cells: vec![None; width * depth * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, x: usize, y: usize, z: usize) -> bool {
+        x < self.width && y < self.depth && z < self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<Cube> {
+        self.cells[self.index(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, cube: Option<Cube>) {
+        let index = self.index(x, y, z);
+        self.cells[index] = cube;
+    }
+
+    /// All the cells stacked over the (x, y) position, from the bottom up.
+    pub fn column(&self, x: usize, y: usize) -> &[Option<Cube>] {
+        let start = self.index(x, y, 0);
+        &self.cells[start..start + self.height]
+    }
+
+    /// Iterates over every occupied cell, yielding its coordinates and the cube.
+    pub fn iter_cubes(&self) -> impl Iterator<Item = (usize, usize, usize, Cube)> + '_ {
+        self.cells.iter().enumerate().filter_map(move |(index, cell)| {
+            let z = index % self.height;
+            let y = (index / self.height) % self.depth;
+            let x = index / (self.height * self.depth);
+            cell.map(|cube| (x, y, z, cube))
+        })
+    }
+
+    /// Returns a grid with the new sizes, keeping every cube that still fits.
+    pub fn resized(&self, width: usize, depth: usize, height: usize) -> CubeGrid {
+        let mut grid = CubeGrid::new(width, depth, height);
+        for (x, y, z, cube) in self.iter_cubes() {
+            if grid.contains(x, y, z) {
+                grid.set(x, y, z, Some(cube));
+            }
+        }
+
+        grid
+    }
+
+    /// Returns the transformed grid, whose sizes are swapped by the rotations.
+    /// `remap` gives the cube that looks like the given one once transformed; if it gives
+    /// None for any cube the grid cannot be transformed, and None is returned.
+    pub fn transformed(&self, transform: GridTransform, remap: impl Fn(Cube) -> Option<Cube>) -> Option<CubeGrid> {
+        let (width, depth, height) = (self.width, self.depth, self.height);
+        let mut grid = match transform {
+            GridTransform::RotateX => CubeGrid::new(width, height, depth),
+            GridTransform::RotateY => CubeGrid::new(height, depth, width),
+            GridTransform::RotateZ => CubeGrid::new(depth, width, height),
+            GridTransform::MirrorX | GridTransform::MirrorY => CubeGrid::new(width, depth, height),
+        };
+        for (x, y, z, cube) in self.iter_cubes() {
+            let (x, y, z) = match transform {
+                GridTransform::RotateX => (x, height - 1 - z, y),
+                GridTransform::RotateY => (z, y, width - 1 - x),
+                GridTransform::RotateZ => (depth - 1 - y, x, z),
+                GridTransform::MirrorX => (width - 1 - x, y, z),
+                GridTransform::MirrorY => (x, depth - 1 - y, z),
+            };
+            grid.set(x, y, z, Some(remap(cube)?));
+        }
+
+        Some(grid)
+    }
+
+    /// World-space position of the center of the grid volume.
+    pub fn center(&self) -> Vec3 {
+        0.5 * vec3(
+            self.width as f32 - 1.0,
+            self.depth as f32 - 1.0,
+            self.height as f32 - 1.0,
+        )
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        debug_assert!(self.contains(x, y, z));
+        (x * self.depth + y) * self.height + z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubes::test_cubemap;
+
+    #[test]
+    fn rotation_moves_cubes_and_swaps_sizes() {
+        let cubemap = test_cubemap();
+        let red = cubemap.find("Red");
+        let mut grid = CubeGrid::new(2, 3, 1);
+        grid.set(0, 0, 0, red);
+
+        let rotated = grid.transformed(GridTransform::RotateZ, Some).unwrap();
+        assert_eq!((rotated.width(), rotated.depth(), rotated.height()), (3, 2, 1));
+        assert_eq!(rotated.get(2, 0, 0), red);
+        assert_eq!(rotated.iter_cubes().count(), 1);
+    }
+
+    #[test]
+    fn transforms_come_back_to_the_start() {
+        let cubemap = test_cubemap();
+        let red_white = cubemap.find("RedWhite").unwrap();
+        let mut grid = CubeGrid::new(3, 2, 2);
+        grid.set(0, 1, 0, Some(red_white));
+        grid.set(2, 0, 1, cubemap.find("BlueRedTop"));
+        let transform = |grid: &CubeGrid, transform: GridTransform| {
+            grid.transformed(transform, |cube| cubemap.transformed(cube, transform.matrix())).unwrap()
+        };
+
+        let mut rotated = grid.clone();
+        for _ in 0..4 {
+            rotated = transform(&rotated, GridTransform::RotateZ);
+        }
+        assert_eq!(rotated, grid);
+        let mirrored = transform(&grid, GridTransform::MirrorX);
+        assert_ne!(mirrored, grid);
+        assert_eq!(transform(&mirrored, GridTransform::MirrorX), grid);
+    }
+
+    #[test]
+    fn cubes_without_a_turned_kind_cannot_be_laid_down() {
+        let cubemap = test_cubemap();
+        let remap = |cube| cubemap.transformed(cube, GridTransform::RotateX.matrix());
+        let mut grid = CubeGrid::new(2, 2, 2);
+        grid.set(0, 0, 0, cubemap.find("Red"));
+        assert!(grid.transformed(GridTransform::RotateX, remap).is_some());
+
+        grid.set(1, 0, 0, cubemap.find("BlueRedTop"));
+        assert!(grid.transformed(GridTransform::RotateX, remap).is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::cubes::Cube;
+use crate::grid::CubeGrid;
+
+/// A single reversible change of the grid.
+enum Edit {
+    Place { x: usize, y: usize, z: usize, cube: Cube },
+    Remove { x: usize, y: usize, z: usize, cube: Cube },
+    /// The cube in the cell was swapped for another one, e.g. a rotated copy.
+    Change { x: usize, y: usize, z: usize, before: Cube, after: Cube },
+    /// The whole grid was swapped, e.g. when loading a file or resizing it.
+    Replace { before: CubeGrid, after: CubeGrid },
+}
+
+impl Edit {
+    fn apply(&self, grid: &mut CubeGrid) {
+        match self {
+            Edit::Place { x, y, z, cube } => grid.set(*x, *y, *z, Some(*cube)),
+            Edit::Remove { x, y, z, .. } => grid.set(*x, *y, *z, None),
+            Edit::Change { x, y, z, after, .. } => grid.set(*x, *y, *z, Some(*after)),
+            Edit::Replace { after, .. } => *grid = after.clone(),
+        }
+    }
+
+    fn revert(&self, grid: &mut CubeGrid) {
+        match self {
+            Edit::Place { x, y, z, .. } => grid.set(*x, *y, *z, None),
+            Edit::Remove { x, y, z, cube } => grid.set(*x, *y, *z, Some(*cube)),
+            Edit::Change { x, y, z, before, .. } => grid.set(*x, *y, *z, Some(*before)),
+            Edit::Replace { before, .. } => *grid = before.clone(),
+        }
+    }
+}
+
+/// Every change of the grid should go through the history, so that it can be undone.
+/// Performing a new edit discards everything that could have been redone.
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+}
+
+impl History {
+    pub fn push_top(&mut self, grid: &mut CubeGrid, x: usize, y: usize, cube: Cube) -> bool {
+        match grid.column(x, y).iter().position(|cell| cell.is_none()) {
+            Some(z) => {
+                self.perform(grid, Edit::Place { x, y, z, cube });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn pop_top(&mut self, grid: &mut CubeGrid, x: usize, y: usize) -> Option<Cube> {
+        let (z, cube) = grid.column(x, y).iter()
+            .enumerate()
+            .rev()
+            .find_map(|(z, cell)| cell.map(|cube| (z, cube)))?;
+        self.perform(grid, Edit::Remove { x, y, z, cube });
+
+        Some(cube)
+    }
+
+    /// Places the cube in a free cell. Returns false if the cell was already taken.
+    pub fn place(&mut self, grid: &mut CubeGrid, x: usize, y: usize, z: usize, cube: Cube) -> bool {
+        if grid.get(x, y, z).is_some() {
+            return false;
+        }
+        self.perform(grid, Edit::Place { x, y, z, cube });
+
+        true
+    }
+
+    pub fn remove(&mut self, grid: &mut CubeGrid, x: usize, y: usize, z: usize) -> Option<Cube> {
+        let cube = grid.get(x, y, z)?;
+        self.perform(grid, Edit::Remove { x, y, z, cube });
+
+        Some(cube)
+    }
+
+    /// Swaps the cube in an occupied cell. Returns false if the cell is empty
+    /// or already holds that cube.
+    pub fn change(&mut self, grid: &mut CubeGrid, x: usize, y: usize, z: usize, cube: Cube) -> bool {
+        match grid.get(x, y, z) {
+            Some(before) if before != cube => {
+                self.perform(grid, Edit::Change { x, y, z, before, after: cube });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn replace(&mut self, grid: &mut CubeGrid, new_grid: CubeGrid) {
+        let before = grid.clone();
+        self.perform(grid, Edit::Replace { before, after: new_grid });
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn undo(&mut self, grid: &mut CubeGrid) {
+        if let Some(edit) = self.undo_stack.pop() {
+            edit.revert(grid);
+            self.redo_stack.push(edit);
+        }
+    }
+
+    pub fn redo(&mut self, grid: &mut CubeGrid) {
+        if let Some(edit) = self.redo_stack.pop() {
+            edit.apply(grid);
+            self.undo_stack.push(edit);
+        }
+    }
+
+    fn perform(&mut self, grid: &mut CubeGrid, edit: Edit) {
+        edit.apply(grid);
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubes::test_cubemap;
+
+    #[test]
+    fn full_and_empty_columns_are_left_alone() {
+        let cubemap = test_cubemap();
+        let red = cubemap.find("Red").unwrap();
+        let mut grid = CubeGrid::new(2, 2, 2);
+        let mut history = History::default();
+
+        assert_eq!(history.pop_top(&mut grid, 0, 0), None);
+        assert!(history.push_top(&mut grid, 0, 0, red));
+        assert!(history.push_top(&mut grid, 0, 0, red));
+        assert!(!history.push_top(&mut grid, 0, 0, red));
+        assert_eq!(grid.column(0, 0), &[Some(red), Some(red)]);
+        assert_eq!(history.pop_top(&mut grid, 0, 0), Some(red));
+        assert_eq!(grid.column(0, 0), &[Some(red), None]);
+    }
+
+    #[test]
+    fn undo_then_redo() {
+        let cubemap = test_cubemap();
+        let (red, blue) = (cubemap.find("Red").unwrap(), cubemap.find("Blue").unwrap());
+        let mut grid = CubeGrid::new(2, 2, 2);
+        let mut history = History::default();
+        history.push_top(&mut grid, 1, 0, red);
+        history.change(&mut grid, 1, 0, 0, blue);
+        let edited = grid.clone();
+
+        history.undo(&mut grid);
+        assert_eq!(grid.get(1, 0, 0), Some(red));
+        history.undo(&mut grid);
+        assert_eq!(grid, CubeGrid::new(2, 2, 2));
+        assert!(!history.can_undo());
+
+        history.redo(&mut grid);
+        history.redo(&mut grid);
+        assert_eq!(grid, edited);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn new_edit_clears_the_redo_stack() {
+        let cubemap = test_cubemap();
+        let red = cubemap.find("Red").unwrap();
+        let mut grid = CubeGrid::new(2, 2, 2);
+        let mut history = History::default();
+        history.push_top(&mut grid, 0, 0, red);
+        history.undo(&mut grid);
+        assert!(history.can_redo());
+
+        history.place(&mut grid, 1, 1, 1, red);
+        assert!(!history.can_redo());
+        history.redo(&mut grid);
+        assert_eq!(grid.get(0, 0, 0), None);
+    }
+
+    #[test]
+    fn undoing_a_replace_restores_the_old_size() {
+        let cubemap = test_cubemap();
+        let mut grid = CubeGrid::new(2, 3, 4);
+        grid.set(1, 2, 0, cubemap.find("Green"));
+        let old = grid.clone();
+        let mut history = History::default();
+
+        history.replace(&mut grid, old.resized(5, 5, 5));
+        assert_eq!((grid.width(), grid.depth(), grid.height()), (5, 5, 5));
+        history.undo(&mut grid);
+        assert_eq!(grid, old);
+    }
+}
@@ -0,0 +1,372 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// Languages the user interface is translated to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    Italian,
+    English,
+    German,
+}
+
+/// Language whose catalog is used for the messages missing from the current one.
+/// It must have every message.
+const FALLBACK: Language = Language::Italian;
+
+static CURRENT_LANGUAGE: AtomicUsize = AtomicUsize::new(0);
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::Italian, Language::English, Language::German];
+
+    /// Name of the language, written in the language itself.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Italian => "Italiano",
+            Language::English => "English",
+            Language::German => "Deutsch",
+        }
+    }
+
+    /// ISO 639-1 code of the language.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Italian => "it",
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    /// Accepts plain codes like "de" as well as locales like "de_CH.UTF-8".
+    pub fn from_code(code: &str) -> Option<Language> {
+        let code = code.get(..2)?.to_ascii_lowercase();
+        Language::ALL.iter().copied().find(|language| language.code() == code)
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::Italian => ITALIAN,
+            Language::English => ENGLISH,
+            Language::German => GERMAN,
+        }
+    }
+}
+
+pub fn language() -> Language {
+    Language::ALL[CURRENT_LANGUAGE.load(Ordering::Relaxed)]
+}
+
+pub fn set_language(language: Language) {
+    let index = Language::ALL.iter().position(|other| *other == language).unwrap_or(0);
+    CURRENT_LANGUAGE.store(index, Ordering::Relaxed);
+}
+
+/// Language of the system, if it is one of the translated ones.
+pub fn system_language() -> Option<Language> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|variable| std::env::var(variable).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| Language::from_code(&value))
+}
+
+/// The message with the given key in the current language. Missing messages are taken from
+/// the fallback language, and if even that one lacks them the key itself is shown, so that
+/// the problem is visible without breaking the interface.
+/// Placeholders like `{count}` are left in place, to be replaced by the caller.
+pub fn tr(key: &'static str) -> &'static str {
+    tr_in(language(), key)
+}
+
+/// Like `tr`, in the given language instead of the current one.
+pub fn tr_in(language: Language, key: &'static str) -> &'static str {
+    lookup(language, key).unwrap_or(key)
+}
+
+/// Name of a palette cube in the current language. Cubes of custom palettes are usually
+/// missing from the catalogs, and keep the name they have in the palette.
+pub fn cube_name(name: &str) -> &str {
+    lookup(language(), &format!("cube.{}", name)).unwrap_or(name)
+}
+
+fn lookup(language: Language, key: &str) -> Option<&'static str> {
+    let find = |language: Language| {
+        language.catalog().iter()
+            .find(|(message_key, _)| *message_key == key)
+            .map(|(_, message)| *message)
+    };
+
+    find(language).or_else(|| find(FALLBACK))
+}
+
+const ITALIAN: &[(&str, &str)] = &[
+    ("app.title", "LaCu - Costruzioni con i cubi"),
+    ("language", "Lingua"),
+    ("builder.undo", "Annulla"),
+    ("builder.redo", "Ripeti"),
+    ("builder.hide_schema", "Nascondi lo schema"),
+    ("builder.show_views", "Mostra le viste"),
+    ("builder.show_paving", "Mostra la scacchiera"),
+    ("builder.available_cubes", "Cubi disponibili"),
+    ("builder.grid_size", "Dimensioni della griglia"),
+    ("support.rule", "Appoggio"),
+    ("support.level", "livello: "),
+    ("support.unsupported", "Cubi senza appoggio: {count}"),
+    ("support.gravity", "Gravità"),
+    ("support.touch_neighbour", "Cubi vicini"),
+    ("support.free", "Libero"),
+    ("transform.title", "Trasforma la costruzione"),
+    ("transform.rotate_x", "Ruota x"),
+    ("transform.rotate_y", "Ruota y"),
+    ("transform.rotate_z", "Ruota z"),
+    ("transform.mirror_x", "Specchia x"),
+    ("transform.mirror_y", "Specchia y"),
+    ("transform.rotate_x.hint", "Ruota di 90° attorno all'asse x"),
+    ("transform.rotate_y.hint", "Ruota di 90° attorno all'asse y"),
+    ("transform.rotate_z.hint", "Ruota di 90° attorno all'asse verticale"),
+    ("transform.mirror_x.hint", "Specchia lungo l'asse x"),
+    ("transform.mirror_y.hint", "Specchia lungo l'asse y"),
+    ("transform.impossible", "Impossibile: alcuni cubi non esistono girati in questo modo"),
+    ("file.title", "File"),
+    ("file.save", "Salva"),
+    ("file.saved", "Costruzione salvata"),
+    ("file.open", "Apri"),
+    ("file.opened", "Costruzione caricata"),
+    ("export.title", "Esporta immagine"),
+    ("export.transparent", "Sfondo trasparente"),
+    ("export.current_view", "Vista attuale"),
+    ("export.all_views", "Tutte le viste"),
+    ("export.done", "Immagine esportata"),
+    ("worksheet.button", "Scheda da stampare"),
+    ("worksheet.saved", "Scheda salvata in {path}"),
+    ("worksheet.title", "Scheda di lavoro"),
+    ("worksheet.schema", "Schema"),
+    ("worksheet.answer", "La tua risposta"),
+    ("exercise.title", "Esercizio"),
+    ("exercise.from_construction", "Dalla costruzione"),
+    ("exercise.from_file", "Dal file"),
+    ("exercise.instructions", "Costruisci una figura che abbia queste viste"),
+    ("exercise.check", "Verifica"),
+    ("exercise.end", "Termina"),
+    ("exercise.correct", "Bravo, la costruzione è corretta!"),
+    ("exercise.wrong", "Non ancora, controlla le viste: {views}"),
+    ("pack.open", "Apri percorso"),
+    ("pack.show", "Mostra il percorso"),
+    ("pack.student", "Studente"),
+    ("pack.level", "Livello {number}"),
+    ("pack.solved", "Livelli risolti: {solved} di {total}"),
+    ("pack.next", "Livello successivo"),
+    ("exercise.wrong_cells", "Non ancora, ci sono {count} caselle diverse: sistema i cubi evidenziati"),
+    ("camera.transition.hint", "Durata del cambio di vista"),
+    ("camera.perspective", "Prospettiva"),
+    ("camera.quad_view", "Quattro viste"),
+    ("camera.fit", "Inquadra"),
+    ("camera.fit.hint", "Inquadra la costruzione"),
+    ("view.front", "Davanti"),
+    ("view.side", "Di lato"),
+    ("view.top", "Dall'alto"),
+    ("export.suffix.front", "davanti"),
+    ("export.suffix.side", "lato"),
+    ("export.suffix.top", "alto"),
+    ("export.suffix.isometric", "assonometria"),
+    ("cube.Green", "Verde"),
+    ("cube.Red", "Rosso"),
+    ("cube.Blue", "Blu"),
+    ("cube.Yellow", "Giallo"),
+    ("cube.White", "Bianco"),
+    ("cube.RedWhite", "Rosso e bianco"),
+    ("cube.BlueRedTop", "Blu con la cima rossa"),
+    ("error.io", "Errore nel file: {error}"),
+    ("error.construction_parse", "Il file non contiene una costruzione valida: {error}"),
+    ("error.construction_version", "Versione del file non supportata: {version}"),
+    ("error.grid_size", "Le dimensioni della griglia devono essere tra 1 e {max}"),
+    ("error.out_of_grid", "Il cubo in ({x}, {y}, {z}) è fuori dalla griglia"),
+    ("error.unknown_cube", "La tavolozza non ha un cubo chiamato {name}"),
+    ("error.pack_parse", "Il file non contiene un percorso valido: {error}"),
+    ("error.pack_version", "Versione del percorso non supportata: {version}"),
+    ("error.no_levels", "Il percorso non ha livelli"),
+    ("error.level", "Livello {number}: {error}"),
+    ("error.export_size", "Le dimensioni dell'immagine devono essere tra 1 e {max}"),
+    ("error.image", "Impossibile salvare l'immagine: {error}"),
+];
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("app.title", "LaCu - Cube constructions"),
+    ("language", "Language"),
+    ("builder.undo", "Undo"),
+    ("builder.redo", "Redo"),
+    ("builder.hide_schema", "Hide the schema"),
+    ("builder.show_views", "Show the views"),
+    ("builder.show_paving", "Show the checkerboard"),
+    ("builder.available_cubes", "Available cubes"),
+    ("builder.grid_size", "Grid size"),
+    ("support.rule", "Support"),
+    ("support.level", "level: "),
+    ("support.unsupported", "Unsupported cubes: {count}"),
+    ("support.gravity", "Gravity"),
+    ("support.touch_neighbour", "Touching cubes"),
+    ("support.free", "Free"),
+    ("transform.title", "Transform the construction"),
+    ("transform.rotate_x", "Rotate x"),
+    ("transform.rotate_y", "Rotate y"),
+    ("transform.rotate_z", "Rotate z"),
+    ("transform.mirror_x", "Mirror x"),
+    ("transform.mirror_y", "Mirror y"),
+    ("transform.rotate_x.hint", "Rotate by 90° around the x axis"),
+    ("transform.rotate_y.hint", "Rotate by 90° around the y axis"),
+    ("transform.rotate_z.hint", "Rotate by 90° around the vertical axis"),
+    ("transform.mirror_x.hint", "Mirror along the x axis"),
+    ("transform.mirror_y.hint", "Mirror along the y axis"),
+    ("transform.impossible", "Not possible: some cubes do not exist turned this way"),
+    ("file.title", "File"),
+    ("file.save", "Save"),
+    ("file.saved", "Construction saved"),
+    ("file.open", "Open"),
+    ("file.opened", "Construction loaded"),
+    ("export.title", "Export picture"),
+    ("export.transparent", "Transparent background"),
+    ("export.current_view", "Current view"),
+    ("export.all_views", "All views"),
+    ("export.done", "Picture exported"),
+    ("worksheet.button", "Printable worksheet"),
+    ("worksheet.saved", "Worksheet saved in {path}"),
+    ("worksheet.title", "Worksheet"),
+    ("worksheet.schema", "Schema"),
+    ("worksheet.answer", "Your answer"),
+    ("exercise.title", "Exercise"),
+    ("exercise.from_construction", "From the construction"),
+    ("exercise.from_file", "From the file"),
+    ("exercise.instructions", "Build a shape with these views"),
+    ("exercise.check", "Check"),
+    ("exercise.end", "Finish"),
+    ("exercise.correct", "Well done, the construction is correct!"),
+    ("exercise.wrong", "Not yet, check these views: {views}"),
+    ("pack.open", "Open pack"),
+    ("pack.show", "Show the pack"),
+    ("pack.student", "Student"),
+    ("pack.level", "Level {number}"),
+    ("pack.solved", "Levels solved: {solved} of {total}"),
+    ("pack.next", "Next level"),
+    ("exercise.wrong_cells", "Not yet, {count} cells are different: fix the highlighted cubes"),
+    ("camera.transition.hint", "Duration of the view change"),
+    ("camera.perspective", "Perspective"),
+    ("camera.quad_view", "Four views"),
+    ("camera.fit", "Fit"),
+    ("camera.fit.hint", "Frame the construction"),
+    ("view.front", "Front"),
+    ("view.side", "Side"),
+    ("view.top", "Top"),
+    ("export.suffix.front", "front"),
+    ("export.suffix.side", "side"),
+    ("export.suffix.top", "top"),
+    ("export.suffix.isometric", "isometric"),
+    ("cube.Green", "Green"),
+    ("cube.Red", "Red"),
+    ("cube.Blue", "Blue"),
+    ("cube.Yellow", "Yellow"),
+    ("cube.White", "White"),
+    ("cube.RedWhite", "Red and white"),
+    ("cube.BlueRedTop", "Blue with a red top"),
+    ("error.io", "File error: {error}"),
+    ("error.construction_parse", "The file does not hold a valid construction: {error}"),
+    ("error.construction_version", "Unsupported file version: {version}"),
+    ("error.grid_size", "Grid sizes must be between 1 and {max}"),
+    ("error.out_of_grid", "The cube at ({x}, {y}, {z}) is outside the grid"),
+    ("error.unknown_cube", "The palette has no cube named {name}"),
+    ("error.pack_parse", "The file does not hold a valid pack: {error}"),
+    ("error.pack_version", "Unsupported pack version: {version}"),
+    ("error.no_levels", "The pack has no levels"),
+    ("error.level", "Level {number}: {error}"),
+    ("error.export_size", "Picture sizes must be between 1 and {max}"),
+    ("error.image", "Could not save the picture: {error}"),
+];
+
+const GERMAN: &[(&str, &str)] = &[
+    ("app.title", "LaCu - Würfelbauten"),
+    ("language", "Sprache"),
+    ("builder.undo", "Rückgängig"),
+    ("builder.redo", "Wiederholen"),
+    ("builder.hide_schema", "Schema ausblenden"),
+    ("builder.show_views", "Ansichten zeigen"),
+    ("builder.show_paving", "Schachbrett zeigen"),
+    ("builder.available_cubes", "Verfügbare Würfel"),
+    ("builder.grid_size", "Rastergröße"),
+    ("support.rule", "Halt"),
+    ("support.level", "Ebene: "),
+    ("support.unsupported", "Würfel ohne Halt: {count}"),
+    ("support.gravity", "Schwerkraft"),
+    ("support.touch_neighbour", "Benachbarte Würfel"),
+    ("support.free", "Frei"),
+    ("transform.title", "Bauwerk umformen"),
+    ("transform.rotate_x", "Drehen x"),
+    ("transform.rotate_y", "Drehen y"),
+    ("transform.rotate_z", "Drehen z"),
+    ("transform.mirror_x", "Spiegeln x"),
+    ("transform.mirror_y", "Spiegeln y"),
+    ("transform.rotate_x.hint", "Um 90° um die x-Achse drehen"),
+    ("transform.rotate_y.hint", "Um 90° um die y-Achse drehen"),
+    ("transform.rotate_z.hint", "Um 90° um die senkrechte Achse drehen"),
+    ("transform.mirror_x.hint", "Entlang der x-Achse spiegeln"),
+    ("transform.mirror_y.hint", "Entlang der y-Achse spiegeln"),
+    ("transform.impossible", "Nicht möglich: manche Würfel gibt es nicht so gedreht"),
+    ("file.title", "Datei"),
+    ("file.save", "Speichern"),
+    ("file.saved", "Bauwerk gespeichert"),
+    ("file.open", "Öffnen"),
+    ("file.opened", "Bauwerk geladen"),
+    ("export.title", "Bild exportieren"),
+    ("export.transparent", "Transparenter Hintergrund"),
+    ("export.current_view", "Aktuelle Ansicht"),
+    ("export.all_views", "Alle Ansichten"),
+    ("export.done", "Bild exportiert"),
+    ("worksheet.button", "Arbeitsblatt zum Drucken"),
+    ("worksheet.saved", "Arbeitsblatt gespeichert in {path}"),
+    ("worksheet.title", "Arbeitsblatt"),
+    ("worksheet.schema", "Schema"),
+    ("worksheet.answer", "Deine Antwort"),
+    ("exercise.title", "Übung"),
+    ("exercise.from_construction", "Aus dem Bauwerk"),
+    ("exercise.from_file", "Aus der Datei"),
+    ("exercise.instructions", "Baue eine Figur mit diesen Ansichten"),
+    ("exercise.check", "Prüfen"),
+    ("exercise.end", "Beenden"),
+    ("exercise.correct", "Super, das Bauwerk ist richtig!"),
+    ("exercise.wrong", "Noch nicht, prüfe diese Ansichten: {views}"),
+    ("pack.open", "Paket öffnen"),
+    ("pack.show", "Paket zeigen"),
+    ("pack.student", "Schüler/in"),
+    ("pack.level", "Stufe {number}"),
+    ("pack.solved", "Gelöste Stufen: {solved} von {total}"),
+    ("pack.next", "Nächste Stufe"),
+    ("exercise.wrong_cells", "Noch nicht, {count} Felder sind anders: korrigiere die markierten Würfel"),
+    ("camera.transition.hint", "Dauer des Ansichtswechsels"),
+    ("camera.perspective", "Perspektive"),
+    ("camera.quad_view", "Vier Ansichten"),
+    ("camera.fit", "Einpassen"),
+    ("camera.fit.hint", "Das Bauwerk einpassen"),
+    ("view.front", "Vorne"),
+    ("view.side", "Seite"),
+    ("view.top", "Oben"),
+    ("export.suffix.front", "vorne"),
+    ("export.suffix.side", "seite"),
+    ("export.suffix.top", "oben"),
+    ("export.suffix.isometric", "isometrie"),
+    ("cube.Green", "Grün"),
+    ("cube.Red", "Rot"),
+    ("cube.Blue", "Blau"),
+    ("cube.Yellow", "Gelb"),
+    ("cube.White", "Weiß"),
+    ("cube.RedWhite", "Rot und weiß"),
+    ("cube.BlueRedTop", "Blau mit roter Oberseite"),
+    ("error.io", "Dateifehler: {error}"),
+    ("error.construction_parse", "Die Datei enthält kein gültiges Bauwerk: {error}"),
+    ("error.construction_version", "Nicht unterstützte Dateiversion: {version}"),
+    ("error.grid_size", "Die Rastergröße muss zwischen 1 und {max} liegen"),
+    ("error.out_of_grid", "Der Würfel bei ({x}, {y}, {z}) liegt außerhalb des Rasters"),
+    ("error.unknown_cube", "Die Palette hat keinen Würfel namens {name}"),
+    ("error.pack_parse", "Die Datei enthält kein gültiges Paket: {error}"),
+    ("error.pack_version", "Nicht unterstützte Paketversion: {version}"),
+    ("error.no_levels", "Das Paket hat keine Stufen"),
+    ("error.level", "Stufe {number}: {error}"),
+    ("error.export_size", "Die Bildgröße muss zwischen 1 und {max} liegen"),
+    ("error.image", "Das Bild konnte nicht gespeichert werden: {error}"),
+];
@@ -4,10 +4,86 @@ extern crate enum_map;
 use macroquad::prelude::*;
 
 mod cubes;
-use cubes::{Cube, create_default_cubemap, CubeMap, CubeInfo};
+use cubes::{Cube, create_cubemap, load_cube_model, CubeMap, CubeInfo};
 
-const GRID_SIZE: usize = 4;
+mod ply;
 
+mod palette;
+use palette::load_palette;
+
+mod assets;
+
+mod construction;
+
+mod grid;
+use grid::{CubeGrid, GridTransform, MAX_GRID_SIZE};
+
+mod history;
+use history::History;
+
+mod projection;
+use projection::{draw_projection, ProjectionView};
+
+mod exercise;
+use exercise::{Check, Exercise};
+
+mod equivalence;
+
+mod pack;
+use pack::Pack;
+
+mod picking;
+use picking::{pick, screen_ray, Pick};
+
+mod support;
+use support::SupportRule;
+
+mod transition;
+use transition::CameraTransition;
+
+mod export;
+use export::{ExportOptions, MAX_EXPORT_SIZE};
+
+mod worksheet;
+
+mod cli;
+
+mod i18n;
+use i18n::{tr, Language};
+
+mod storage;
+
+mod settings;
+use settings::Settings;
+
+/// Mouse movement, in pixels, below which a press and release is a click rather than a drag
+const CLICK_TOLERANCE: f32 = 4.0;
+/// Radians the orbit camera turns for each pixel the mouse is dragged
+const ORBIT_SPEED: f32 = 0.01;
+/// Limits of the angle between the orbit camera direction and the paving, in radians
+const MIN_ORBIT_PITCH: f32 = -0.2;
+const MAX_ORBIT_PITCH: f32 = 1.5;
+/// Vertical field of view of the perspective cameras, in radians (45 degrees)
+const PERSPECTIVE_FOVY: f32 = std::f32::consts::FRAC_PI_4;
+/// Zoom is relative to the framing of the camera presets
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.0;
+/// Zoom factor applied for every notch of the mouse wheel
+const ZOOM_STEP: f32 = 1.1;
+/// Space left around the construction when framing it, in cubes
+const FIT_MARGIN: f32 = 1.0;
+/// Seconds the window must keep its size before the size is saved, so that it is not
+/// written at every frame while the user is resizing it
+const WINDOW_SIZE_SAVE_DELAY: f64 = 0.5;
+
+/// Pictures to export once the frame has been drawn.
+enum ExportRequest {
+    CurrentView,
+    /// Every camera preset, each one in its own file
+    Presets,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Enum, serde::Serialize, serde::Deserialize)]
 enum CameraView {
     Front,
     Side,
@@ -15,43 +91,99 @@ enum CameraView {
     Isometric
 }
 
-type CubeColumn = [Option<Cube>; GRID_SIZE];
-type CubeGrid = [[[Option<Cube>; GRID_SIZE]; GRID_SIZE]; GRID_SIZE];
-
-fn window_conf() -> Conf {
+fn window_conf(settings: &Settings) -> Conf {
     Conf {
-        window_title: "ColorCubes".to_owned(),
+        window_title: tr("app.title").to_owned(),
+        window_width: settings.window_width,
+        window_height: settings.window_height,
         sample_count: 8,
         fullscreen: false,
         ..Default::default()
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let mut camera_view = CameraView::Isometric;
-    let mut camera = create_isometric_camera();
+fn main() {
+    // with any argument, run the command line tools, which need no window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(language) = i18n::system_language() {
+        i18n::set_language(language);
+    }
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+    let settings = settings::load_settings();
+    if let Some(language) = settings.language {
+        i18n::set_language(language);
+    }
+    macroquad::Window::from_config(window_conf(&settings), run(settings));
+}
+
+async fn run(mut saved_settings: Settings) {
+    let mut cubes = CubeGrid::default();
+    let mut history = History::default();
+    let mut camera_view = saved_settings.camera_view;
+    let mut perspective = saved_settings.perspective;
+    let mut camera = create_camera(&camera_view, &cubes, perspective, 1.0);
+    let mut camera_transition: Option<CameraTransition> = None;
+    let mut transition_duration = saved_settings.transition_duration;
+    let mut zoom_levels: enum_map::EnumMap<CameraView, f32> = enum_map! { _ => 1.0 };
+    // distance between the fingers during a pinch gesture
+    let mut pinch_distance: Option<f32> = None;
+    // the quad view shows every view at once, each one through its own camera
+    let mut quad_view = saved_settings.quad_view;
+    let mut quad_cameras: enum_map::EnumMap<CameraView, Camera3D> = enum_map! {
+        view => create_camera(&view, &cubes, perspective, 1.0),
+    };
+    // view whose pane is being dragged with the mouse
+    let mut dragged_view: Option<CameraView> = None;
 
-    let cubemap = create_default_cubemap();
+    let cube_model = load_cube_model().await;
+    let palette = load_palette().await;
+    let cubemap = create_cubemap(palette, &cube_model);
 
-    let mut show_paving: bool = true;
-    let mut hide_builder: bool = false;
+    let mut show_paving: bool = saved_settings.show_paving;
+    let mut hide_builder: bool = saved_settings.hide_builder;
+    let mut show_projections: bool = saved_settings.show_projections;
     let mut dragged_cube: Option<Cube> = None;
+    let mut selected_cube: Option<Cube> = None;
+    let mut click_position: Option<Vec2> = None;
+    let mut right_click_position: Option<Vec2> = None;
+    let mut support_rule = saved_settings.support_rule;
+    // None until the user picks a language, so that the one of the system is followed
+    let mut chosen_language = saved_settings.language;
+    // layer where the cubes dropped on the builder go, when they are not stacked
+    let mut placement_level: usize = 0;
 
-    let mut cubes : CubeGrid = CubeGrid::default();
+    let mut file_name = String::from("costruzione.ron");
+    let mut file_status = String::new();
+    let mut export_options = ExportOptions::default();
+    let mut export_request: Option<ExportRequest> = None;
+    let mut exercise: Option<Exercise> = None;
+    let mut exercise_result: Option<Check> = None;
+    let mut pack: Option<Pack> = None;
+    let mut show_pack = false;
+    // level of the pack the exercise comes from, if any
+    let mut current_level: Option<usize> = None;
+    let mut progress = pack::load_progress();
     let mut saved_mouse_position: Vec2 = mouse_position().into();
+    let mut window_size = (screen_width() as i32, screen_height() as i32);
+    let mut window_resized_at = get_time();
 
     show_mouse(false);
+    // closing the window goes through the end of the loop, where the settings are saved
+    prevent_quit();
 
     loop {
         let delta = get_frame_time();
 
-        let mut additional_zoom = 0.0;
+        let grid_size = (cubes.width(), cubes.depth(), cubes.height());
         clear_background(WHITE);
 
-        if is_key_pressed(KeyCode::Escape) {
-            break;
-        }
+        let quitting = is_key_pressed(KeyCode::Escape) || is_quit_requested();
+        // set by the widgets of the settings, which are saved only when the user changes them
+        let mut settings_changed = false;
+
+        let unsupported = support::unsupported_cubes(&cubes, support_rule);
 
         // Whole egui UI
         let vertical_display = screen_height() > screen_width();
@@ -68,9 +200,20 @@ async fn main() {
                         let colored_square_size = ui.spacing().interact_size.y * egui::vec2(2.5, 2.5);
                         let colored_square_spacing = ui.spacing().interact_size.y * egui::vec2(0.2, 0.2);
                         let shrink_amount = ui.spacing().interact_size.y * egui::vec2(0.25, 0.25);
-                        ui.checkbox(&mut hide_builder, "Nascondi lo schema");
-                        if hide_builder {
-                            let total_size = colored_square_size * GRID_SIZE as f32 + colored_square_spacing * (GRID_SIZE - 1) as f32;
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(history.can_undo(), egui::Button::new(tr("builder.undo"))).clicked() {
+                                history.undo(&mut cubes);
+                            }
+                            if ui.add_enabled(history.can_redo(), egui::Button::new(tr("builder.redo"))).clicked() {
+                                history.redo(&mut cubes);
+                            }
+                        });
+                        // some exercises must be solved without the schema
+                        let schema_forced = exercise.as_ref().is_some_and(Exercise::hides_schema);
+                        settings_changed |= ui.add_enabled(!schema_forced, egui::Checkbox::new(&mut hide_builder, tr("builder.hide_schema"))).changed();
+                        if hide_builder || schema_forced {
+                            let squares = egui::vec2(cubes.depth() as f32, cubes.width() as f32);
+                            let total_size = colored_square_size * squares + colored_square_spacing * (squares - egui::vec2(1.0, 1.0));
                             let (rect, _response) = ui.allocate_exact_size(total_size, egui::Sense::click());
                             let label = egui::widgets::Label::new(egui::RichText::new("?").color(egui::Color32::KHAKI).size(72.0));
                             ui.put(rect, label);
@@ -78,23 +221,60 @@ async fn main() {
                             egui::Grid::new("cube_builder")
                                 .spacing(colored_square_spacing)
                                 .show(ui, |ui| {
-                                    for x in 0..GRID_SIZE {
-                                        for y in 0..GRID_SIZE {
+                                    for x in 0..cubes.width() {
+                                        for y in 0..cubes.depth() {
                                             let (rect, response) = ui.allocate_exact_size(colored_square_size, egui::Sense::click());
                                             if response.double_clicked() {
-                                                pop_top(&mut cubes, x, y);
+                                                history.pop_top(&mut cubes, x, y);
+                                            }
+                                            // a right click spins the cube on top of the column
+                                            if response.secondary_clicked() {
+                                                let top = cubes.column(x, y).iter().rposition(|cell| cell.is_some());
+                                                if let Some(z) = top {
+                                                    let rotated = cubemap.rotated(cubes.get(x, y, z).unwrap(), 1);
+                                                    history.change(&mut cubes, x, y, z, rotated);
+                                                }
                                             }
                                             if let Some(dropped_cube) = dragged_cube {
-                                                if ui.input().pointer.any_released() && response.hovered() {
-                                                    push_top(&mut cubes, x, y, dropped_cube);
+                                                let available = exercise.as_ref().is_none_or(|exercise| exercise.can_use(dropped_cube, &cubes));
+                                                if ui.input().pointer.any_released() && response.hovered() && available {
+                                                    if support_rule == SupportRule::Gravity {
+                                                        history.push_top(&mut cubes, x, y, dropped_cube);
+                                                    } else if support::can_place(&cubes, support_rule, x, y, placement_level) {
+                                                        history.place(&mut cubes, x, y, placement_level, dropped_cube);
+                                                    }
                                                 }
                                             }
-                                            draw_column(&cubes[x][y], &cubemap, ui, rect, shrink_amount);
+                                            draw_column(cubes.column(x, y), &cubemap, ui, rect, shrink_amount);
                                         }
                                         ui.end_row();
                                     }
                                 });
 
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_label(tr("support.rule"))
+                                    .selected_text(support_rule_name(support_rule))
+                                    .show_ui(ui, |ui| {
+                                        for rule in SupportRule::ALL {
+                                            settings_changed |= ui.selectable_value(&mut support_rule, rule, support_rule_name(rule)).changed();
+                                        }
+                                    });
+                                if support_rule != SupportRule::Gravity {
+                                    placement_level = placement_level.min(cubes.height() - 1);
+                                    ui.add(egui::DragValue::new(&mut placement_level).clamp_range(0..=cubes.height() - 1).prefix(tr("support.level")));
+                                }
+                            });
+                            if !unsupported.is_empty() {
+                                ui.colored_label(egui::Color32::RED, tr("support.unsupported").replace("{count}", &unsupported.len().to_string()));
+                            }
+
+                            settings_changed |= ui.checkbox(&mut show_projections, tr("builder.show_views")).changed();
+                            if show_projections {
+                                let projections: Vec<_> = ProjectionView::ALL.iter()
+                                    .map(|&view| (view, projection::Projection::compute(&cubes, &cubemap, view)))
+                                    .collect();
+                                draw_projection_views(ui, &projections, colored_square_size * 0.3);
+                            }
                         }
                         if ui.input().pointer.any_released() {
                             dragged_cube = None;
@@ -102,21 +282,176 @@ async fn main() {
                     });
                     ui.vertical(|ui| {
                         use itertools::Itertools;
-                        ui.label("Cubi disponibili");
+                        ui.label(tr("builder.available_cubes"));
                         let square_size = ui.spacing().interact_size.y * egui::vec2(2.0, 2.0);
-                        for line in &cubemap.iter().chunks(5) {
+                        let available_cubes = cubemap.iter()
+                            .filter(|(cube, _)| exercise.as_ref().is_none_or(|exercise| exercise.allows(*cube)));
+                        for line in &available_cubes.chunks(5) {
                             ui.horizontal(|ui| {
                                 for entry in line {
                                     let cube: Cube = entry.0;
                                     let info: &CubeInfo = entry.1;
-                                    if draw_drag_cube(ui, info.egui_shape.clone(), square_size) {
+                                    let remaining = exercise.as_ref().and_then(|exercise| exercise.remaining(cube, &cubes));
+                                    let response = draw_drag_cube(ui, cubemap.top_shape(cube), square_size, selected_cube == Some(cube), remaining);
+                                    if response.drag_started() {
                                         dragged_cube = Some(cube);
                                     }
+                                    if response.drag_started() || response.clicked() {
+                                        selected_cube = Some(cube);
+                                    }
+                                    response.on_hover_text(i18n::cube_name(&info.name));
                                 }
                             });
                             ui.add_space(2.0);
                         }
-                        ui.checkbox(&mut show_paving, "Mostra la scacchiera");
+                        settings_changed |= ui.checkbox(&mut show_paving, tr("builder.show_paving")).changed();
+                        ui.separator();
+                        // during an exercise the grid must keep the size of the target, and the student
+                        // must not be able to load it or reshape the construction all at once
+                        let in_exercise = exercise.is_some();
+                        ui.label(tr("builder.grid_size"));
+                        let (mut width, mut depth, mut height) = (cubes.width(), cubes.depth(), cubes.height());
+                        ui.horizontal(|ui| {
+                            ui.set_enabled(!in_exercise);
+                            let size_range = 1..=MAX_GRID_SIZE;
+                            ui.add(egui::DragValue::new(&mut width).clamp_range(size_range.clone()).prefix("x: "));
+                            ui.add(egui::DragValue::new(&mut depth).clamp_range(size_range.clone()).prefix("y: "));
+                            ui.add(egui::DragValue::new(&mut height).clamp_range(size_range).prefix("z: "));
+                        });
+                        if (width, depth, height) != (cubes.width(), cubes.depth(), cubes.height()) {
+                            let resized = cubes.resized(width, depth, height);
+                            history.replace(&mut cubes, resized);
+                        }
+                        ui.label(tr("transform.title"));
+                        ui.horizontal(|ui| {
+                            ui.set_enabled(!in_exercise);
+                            let mut transform = None;
+                            for (text, hover_text, grid_transform) in [
+                                (tr("transform.rotate_x"), tr("transform.rotate_x.hint"), GridTransform::RotateX),
+                                (tr("transform.rotate_y"), tr("transform.rotate_y.hint"), GridTransform::RotateY),
+                                (tr("transform.rotate_z"), tr("transform.rotate_z.hint"), GridTransform::RotateZ),
+                                (tr("transform.mirror_x"), tr("transform.mirror_x.hint"), GridTransform::MirrorX),
+                                (tr("transform.mirror_y"), tr("transform.mirror_y.hint"), GridTransform::MirrorY),
+                            ] {
+                                if ui.button(text).on_hover_text(hover_text).clicked() {
+                                    transform = Some(grid_transform);
+                                }
+                            }
+                            if let Some(transform) = transform {
+                                let matrix = transform.matrix();
+                                // some cubes, like the ones with a colored top, have no kind of the palette
+                                // that looks like them once laid on their side
+                                match cubes.transformed(transform, |cube| cubemap.transformed(cube, matrix)) {
+                                    Some(mut transformed) => {
+                                        // turning the construction on its side may leave cubes hanging
+                                        if support_rule == SupportRule::Gravity {
+                                            transformed = support::settled(&transformed);
+                                        }
+                                        history.replace(&mut cubes, transformed);
+                                    }
+                                    None => file_status = tr("transform.impossible").to_owned(),
+                                }
+                            }
+                        });
+                        ui.separator();
+                        ui.label(tr("file.title"));
+                        ui.text_edit_singleline(&mut file_name);
+                        ui.horizontal(|ui| {
+                            if ui.button(tr("file.save")).clicked() {
+                                file_status = match construction::save_to_file(&file_name, &cubes, &cubemap) {
+                                    Ok(()) => tr("file.saved").to_owned(),
+                                    Err(err) => err.message(i18n::language()),
+                                };
+                            }
+                            if ui.add_enabled(!in_exercise, egui::Button::new(tr("file.open"))).clicked() {
+                                file_status = match construction::load_from_file(&file_name, &cubemap) {
+                                    Ok(grid) => {
+                                        history.replace(&mut cubes, grid);
+                                        tr("file.opened").to_owned()
+                                    }
+                                    Err(err) => err.message(i18n::language()),
+                                };
+                            }
+                        });
+                        ui.label(tr("export.title"));
+                        ui.horizontal(|ui| {
+                            let size_range = 1..=MAX_EXPORT_SIZE;
+                            ui.add(egui::DragValue::new(&mut export_options.width).clamp_range(size_range.clone()).suffix(" px"));
+                            ui.label("×");
+                            ui.add(egui::DragValue::new(&mut export_options.height).clamp_range(size_range).suffix(" px"));
+                        });
+                        ui.checkbox(&mut export_options.transparent, tr("export.transparent"));
+                        ui.horizontal(|ui| {
+                            if ui.button(tr("export.current_view")).clicked() {
+                                export_request = Some(ExportRequest::CurrentView);
+                            }
+                            if ui.button(tr("export.all_views")).clicked() {
+                                export_request = Some(ExportRequest::Presets);
+                            }
+                        });
+                        if ui.button(tr("worksheet.button")).clicked() {
+                            let path = std::path::Path::new(&file_name).with_extension("svg");
+                            file_status = match worksheet::save_worksheet(&path, &cubes, &cubemap) {
+                                Ok(()) => tr("worksheet.saved").replace("{path}", &path.display().to_string()),
+                                Err(err) => tr("error.io").replace("{error}", &err.to_string()),
+                            };
+                        }
+                        if !file_status.is_empty() {
+                            ui.label(&file_status);
+                        }
+                        ui.separator();
+                        ui.label(tr("exercise.title"));
+                        ui.horizontal(|ui| {
+                            let mut target = None;
+                            if ui.button(tr("exercise.from_construction")).clicked() {
+                                target = Some(cubes.clone());
+                            }
+                            if ui.button(tr("exercise.from_file")).clicked() {
+                                match construction::load_from_file(&file_name, &cubemap) {
+                                    Ok(grid) => target = Some(grid),
+                                    Err(err) => file_status = err.message(i18n::language()),
+                                }
+                            }
+                            // the student starts from scratch, and must not be able to undo back to the target
+                            if let Some(target) = target {
+                                let new_exercise = Exercise::new(&target, &cubemap);
+                                cubes = new_exercise.empty_grid();
+                                history = History::default();
+                                exercise = Some(new_exercise);
+                                exercise_result = None;
+                                current_level = None;
+                            }
+                        });
+                        // another pack would take the place of the one whose level is being solved
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(!in_exercise, egui::Button::new(tr("pack.open"))).clicked() {
+                                match pack::load_pack(&file_name, &cubemap) {
+                                    Ok(loaded) => {
+                                        pack = Some(loaded);
+                                        show_pack = true;
+                                    }
+                                    Err(err) => file_status = err.message(i18n::language()),
+                                }
+                            }
+                            if pack.is_some() {
+                                ui.checkbox(&mut show_pack, tr("pack.show"));
+                            }
+                        });
+                        ui.separator();
+                        let mut language = i18n::language();
+                        egui::ComboBox::from_label(tr("language"))
+                            .selected_text(language.name())
+                            .show_ui(ui, |ui| {
+                                for other in Language::ALL {
+                                    ui.selectable_value(&mut language, other, other.name());
+                                }
+                            });
+                        // the window title keeps the language of the start, it cannot change afterwards
+                        if language != i18n::language() {
+                            i18n::set_language(language);
+                            chosen_language = Some(language);
+                            settings_changed = true;
+                        }
                     });
                 });
             };
@@ -129,32 +464,159 @@ async fn main() {
                     .resizable(false)
                     .show(egui_ctx, cube_builder_lambda);
             }
+            let mut start_level: Option<usize> = None;
+            if let (true, Some(current_pack)) = (show_pack, &pack) {
+                egui::Window::new(&current_pack.name)
+                    .id(egui::Id::new("pack_window"))
+                    .resizable(false)
+                    .show(egui_ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(tr("pack.student"));
+                            if ui.text_edit_singleline(&mut progress.student).changed() {
+                                pack::save_progress(&progress);
+                            }
+                        });
+                        let solved = progress.solved_levels(current_pack);
+                        for (index, level) in current_pack.levels.iter().enumerate() {
+                            let mut title = if level.title.is_empty() {
+                                tr("pack.level").replace("{number}", &(index + 1).to_string())
+                            } else {
+                                level.title.clone()
+                            };
+                            if index < solved {
+                                title = format!("✔ {}", title);
+                            }
+                            let unlocked = progress.is_unlocked(current_pack, index);
+                            if ui.add_enabled(unlocked, egui::Button::new(title)).clicked() {
+                                start_level = Some(index);
+                            }
+                        }
+                        ui.label(tr("pack.solved")
+                            .replace("{solved}", &solved.min(current_pack.levels.len()).to_string())
+                            .replace("{total}", &current_pack.levels.len().to_string()));
+                    });
+            }
+            let mut close_exercise = false;
+            if let Some(current_exercise) = &exercise {
+                egui::Window::new(tr("exercise.title"))
+                    .id(egui::Id::new("exercise_window"))
+                    .resizable(false)
+                    .show(egui_ctx, |ui| {
+                        ui.label(tr("exercise.instructions"));
+                        let cell_size = ui.spacing().interact_size.y * egui::vec2(1.2, 1.2);
+                        draw_projection_views(ui, current_exercise.projections(), cell_size);
+                        ui.horizontal(|ui| {
+                            if ui.button(tr("exercise.check")).clicked() {
+                                let check = current_exercise.check(&cubes, &cubemap);
+                                if let (true, Some(level), Some(current_pack)) = (check.is_solved(), current_level, &pack) {
+                                    progress.mark_solved(current_pack, level);
+                                    pack::save_progress(&progress);
+                                }
+                                exercise_result = Some(check);
+                            }
+                            if ui.button(tr("exercise.end")).clicked() {
+                                close_exercise = true;
+                            }
+                        });
+                        match &exercise_result {
+                            Some(check) if check.is_solved() => {
+                                ui.colored_label(egui::Color32::GREEN, tr("exercise.correct"));
+                                let next_level = current_level.map(|level| level + 1)
+                                    .filter(|&level| pack.as_ref().is_some_and(|current_pack| level < current_pack.levels.len()));
+                                if let Some(level) = next_level {
+                                    if ui.button(tr("pack.next")).clicked() {
+                                        start_level = Some(level);
+                                    }
+                                }
+                            }
+                            // the views of the target are not the ones to look at when it may be turned
+                            Some(Check { comparison: Some(comparison), .. }) => {
+                                let count = comparison.differences.len().to_string();
+                                ui.colored_label(egui::Color32::RED, tr("exercise.wrong_cells").replace("{count}", &count));
+                            }
+                            Some(check) => {
+                                let names: Vec<&str> = check.wrong_views.iter().map(|view| view.name()).collect();
+                                ui.colored_label(egui::Color32::RED, tr("exercise.wrong").replace("{views}", &names.join(", ")));
+                            }
+                            None => {}
+                        }
+                    });
+            }
+            if close_exercise {
+                exercise = None;
+                exercise_result = None;
+                current_level = None;
+            }
+            if let (Some(level), Some(current_pack)) = (start_level, &pack) {
+                let new_exercise = Exercise::from_level(&current_pack.levels[level], &cubemap);
+                cubes = new_exercise.empty_grid();
+                history = History::default();
+                selected_cube = selected_cube.filter(|&cube| new_exercise.allows(cube));
+                exercise = Some(new_exercise);
+                exercise_result = None;
+                current_level = Some(level);
+            }
             // BOT BAR: camera
             egui::TopBottomPanel::bottom("camera_panel").show(egui_ctx, |ui| {
                 ui.horizontal(|ui| {
                     let camera_button_size = ui.spacing().interact_size.y * egui::vec2(3.0, 3.0);
+                    let mut new_view = None;
                     if draw_x_icon(ui, camera_button_size).clicked() {
-                        camera = create_front_camera();
-                        camera_view = CameraView::Front;
+                        new_view = Some(CameraView::Front);
                     };
                     if draw_y_icon(ui, camera_button_size).clicked() {
-                        camera = create_side_camera();
-                        camera_view = CameraView::Side;
+                        new_view = Some(CameraView::Side);
                     }
                     if draw_z_icon(ui, camera_button_size).clicked() {
-                        camera = create_top_camera();
-                        camera_view = CameraView::Top;
+                        new_view = Some(CameraView::Top);
                     }
                     if draw_xyz_icon(ui, camera_button_size).clicked() {
-                        camera = create_isometric_camera();
-                        camera_view = CameraView::Isometric;
+                        new_view = Some(CameraView::Isometric);
+                    }
+                    if let Some(new_view) = new_view {
+                        quad_view = false;
+                        let goal = create_camera(&new_view, &cubes, perspective, zoom_levels[new_view]);
+                        camera_transition = Some(CameraTransition::new(camera, goal, transition_duration));
+                        camera_view = new_view;
+                        settings_changed = true;
+                    }
+                    let duration_response = ui.add(egui::DragValue::new(&mut transition_duration)
+                        .clamp_range(0.0..=3.0)
+                        .speed(0.05)
+                        .suffix(" s"))
+                        .on_hover_text(tr("camera.transition.hint"));
+                    // while the value is dragged it changes at every frame, so it is saved once released
+                    settings_changed |= duration_response.drag_released() || (duration_response.changed() && !duration_response.dragged());
+                    let perspective_changed = ui.checkbox(&mut perspective, tr("camera.perspective")).changed();
+                    let quad_view_changed = ui.checkbox(&mut quad_view, tr("camera.quad_view")).changed();
+                    if perspective_changed || quad_view_changed {
+                        settings_changed = true;
+                        camera_transition = None;
+                        camera = create_camera(&camera_view, &cubes, perspective, zoom_levels[camera_view]);
+                        for (view, quad_camera) in quad_cameras.iter_mut() {
+                            *quad_camera = create_camera(&view, &cubes, perspective, zoom_levels[view]);
+                        }
+                    }
+                    if ui.button(tr("camera.fit")).on_hover_text(tr("camera.fit.hint")).clicked() {
+                        if quad_view {
+                            for (view, quad_camera) in quad_cameras.iter_mut() {
+                                let (goal, zoom) = fit_camera(quad_camera, &view, &cubes, perspective);
+                                zoom_levels[view] = zoom;
+                                *quad_camera = goal;
+                            }
+                        } else {
+                            let (goal, zoom) = fit_camera(&camera, &camera_view, &cubes, perspective);
+                            zoom_levels[camera_view] = zoom;
+                            camera_transition = Some(CameraTransition::new(camera, goal, transition_duration));
+                        }
                     }
 
                     let rich_text = egui::RichText::new("🔎")
                         .size(32.0)
                         .color(egui::Color32::WHITE);
                     ui.label(rich_text);
-                    let zoom_slider = egui::Slider::new(&mut additional_zoom, -2.0..=2.0)
+                    let zoom_slider = egui::Slider::new(&mut zoom_levels[camera_view], MIN_ZOOM..=MAX_ZOOM)
+                        .logarithmic(true)
                         .show_value(false);
                     ui.add_sized(ui.available_size(), zoom_slider);
                 });
@@ -162,83 +624,236 @@ async fn main() {
         });
 
         let mut egui_mouse_requested = false;
+        let mut egui_keyboard_requested = false;
         let mut viewport_area = (0, 0, 0, 0);
 
         egui_macroquad::cfg(|egui_ctx: &egui::Context| {
             egui_mouse_requested = egui_ctx.wants_pointer_input() || egui_ctx.is_pointer_over_area();
+            egui_keyboard_requested = egui_ctx.wants_keyboard_input();
             let free_area = egui_ctx.available_rect();
             viewport_area = (0, (screen_height() - free_area.max.y) as i32, free_area.size().x as i32, free_area.size().y as i32);
         });
 
+        // undo/redo shortcuts, unless a text field is being edited
+        let control_down = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let shift_down = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        if control_down && !egui_keyboard_requested {
+            if is_key_pressed(KeyCode::Y) || (shift_down && is_key_pressed(KeyCode::Z)) {
+                history.redo(&mut cubes);
+            } else if is_key_pressed(KeyCode::Z) {
+                history.undo(&mut cubes);
+            }
+        }
+
+        // a different grid needs a different framing, reset the camera presets
+        if grid_size != (cubes.width(), cubes.depth(), cubes.height()) {
+            camera_transition = None;
+            camera = create_camera(&camera_view, &cubes, perspective, zoom_levels[camera_view]);
+            for (view, quad_camera) in quad_cameras.iter_mut() {
+                *quad_camera = create_camera(&view, &cubes, perspective, zoom_levels[view]);
+            }
+        }
+
+        // the 3D area is either a single pane, or split in four panes, one for each view
+        let panes = if quad_view {
+            split_viewport(viewport_area)
+        } else {
+            vec![(camera_view, viewport_area)]
+        };
+        let mouse_position: Vec2 = mouse_position().into();
+        let hovered_view = if egui_mouse_requested {
+            None
+        } else {
+            panes.iter()
+                .find(|(_, viewport)| viewport_contains(*viewport, mouse_position))
+                .map(|(view, _)| *view)
+        };
 
-        // camera control
+        // camera control: the left button orbits around the construction in the isometric view,
+        // any other drag moves the camera
         if !egui_mouse_requested {
-            // TODO: check if this is needed for real, or everything still works even if we do not
-            // separate the first click-to-save position
             if is_mouse_button_pressed(MouseButton::Left) {
-                saved_mouse_position = mouse_position().into();
-            } else if is_mouse_button_down(MouseButton::Left) {
-                let mouse_position: Vec2 = mouse_position().into();
-                let mouse_delta = mouse_position - saved_mouse_position;
+                click_position = Some(mouse_position);
+            }
+            if is_mouse_button_pressed(MouseButton::Right) {
+                right_click_position = Some(mouse_position);
+            }
+            if is_mouse_button_pressed(MouseButton::Left)
+                || is_mouse_button_pressed(MouseButton::Right)
+                || is_mouse_button_pressed(MouseButton::Middle) {
                 saved_mouse_position = mouse_position;
-                // move camera, something different can happen depending on the active view
-                let scale = 0.02;
-                match camera_view {
-                    CameraView::Top => {
-                        camera.target.x -= scale * mouse_delta.y;
-                        camera.position.x -= scale * mouse_delta.y;
-                        camera.target.y -= scale * mouse_delta.x;
-                        camera.position.y -= scale * mouse_delta.x;
-                    }
-                    CameraView::Front => {
-                        camera.target.z += scale * mouse_delta.y;
-                        camera.position.z += scale * mouse_delta.y;
-                        camera.target.y -= scale * mouse_delta.x;
-                        camera.position.y -= scale * mouse_delta.x;
-                    }
-                    CameraView::Side => {
-                        camera.target.z += scale * mouse_delta.y;
-                        camera.position.z += scale * mouse_delta.y;
-                        camera.target.x -= scale * mouse_delta.x;
-                        camera.position.x -= scale * mouse_delta.x;
-                    }
-                    CameraView::Isometric => {
-                        let camera_dir = camera.target - camera.position;
-                        let camera_left = camera.up.cross(camera_dir).normalize();
-                        camera.target += scale * (mouse_delta.x*camera_left + mouse_delta.y * camera.up);
-                        camera.position += scale * (mouse_delta.x*camera_left + mouse_delta.y * camera.up);
-                    }
-                }
+                dragged_view = hovered_view;
+            }
+            let mouse_delta = mouse_position - saved_mouse_position;
+            saved_mouse_position = mouse_position;
+
+            let panning = is_mouse_button_down(MouseButton::Right) || is_mouse_button_down(MouseButton::Middle);
+            if let Some(view) = dragged_view.filter(|_| panning || is_mouse_button_down(MouseButton::Left)) {
+                let pane_camera = if quad_view {
+                    &mut quad_cameras[view]
+                } else {
+                    camera_transition = None;
+                    &mut camera
+                };
+                move_camera(pane_camera, view, cubes.center(), mouse_delta, panning);
             }
         }
 
-        camera.viewport = Some(viewport_area);
-        camera.aspect = Some(viewport_area.2 as f32 / viewport_area.3 as f32);
-        camera.fovy -= additional_zoom * delta;
-        set_camera(&camera);
-
-        for x in 0..GRID_SIZE {
-            for y in 0..GRID_SIZE {
-                if show_paving {
-                    // add some cubes to be used as a "pavement"
-                    let paving_color = if (x+y) % 2 == 0 {
-                        GRAY
-                    } else {
-                        LIGHTGRAY
-                    };
-                    draw_cube(vec3(x as f32, y as f32, -0.6), vec3(1.0, 1.0, 0.2), None, paving_color);
+        if let Some(transition) = &mut camera_transition {
+            if transition.update(&mut camera, delta) {
+                camera_transition = None;
+            }
+        }
+
+        // zoom with the mouse wheel or by pinching the 3D view
+        if let Some(view) = hovered_view {
+            let (_, wheel) = mouse_wheel();
+            if wheel != 0.0 {
+                zoom_levels[view] *= ZOOM_STEP.powf(wheel.signum());
+            }
+        }
+        let current_touches = touches();
+        if let [first, second] = current_touches.as_slice() {
+            let distance = first.position.distance(second.position);
+            if let Some(previous) = pinch_distance {
+                if previous > 0.0 && !egui_mouse_requested {
+                    zoom_levels[hovered_view.unwrap_or(camera_view)] *= distance / previous;
                 }
-                for z in 0..GRID_SIZE {
-                    if let Some(cube) = cubes[x][y][z] {
-                        let model_matrix = Mat4::from_translation(vec3(x as f32, y as f32, z as f32));
-                        let gl = unsafe { get_internal_gl().quad_gl };
-                        gl.push_model_matrix(model_matrix);
-                        draw_mesh(&cubemap[cube].mesh);
-                        gl.pop_model_matrix();
-                    }
+            }
+            pinch_distance = Some(distance);
+        } else {
+            pinch_distance = None;
+        }
+        for zoom in zoom_levels.values_mut() {
+            *zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+
+        for &(view, viewport) in panes.iter() {
+            let preset = create_camera(&view, &cubes, perspective, 1.0);
+            let pane_camera = if quad_view { &mut quad_cameras[view] } else { &mut camera };
+            pane_camera.viewport = Some(viewport);
+            pane_camera.aspect = Some(viewport.2 as f32 / viewport.3 as f32);
+            if quad_view || camera_transition.is_none() {
+                apply_zoom(pane_camera, &preset, zoom_levels[view]);
+            }
+        }
+
+        // picking: a left click places the selected cube next to the face under the mouse,
+        // a right click removes the cube under the mouse
+        let picked = hovered_view
+            .and_then(|view| screen_ray(if quad_view { &quad_cameras[view] } else { &camera }, mouse_position))
+            .and_then(|(origin, direction)| pick(&cubes, origin, direction));
+        let target_cell = picked.and_then(|picked| picked.target_cell(&cubes));
+        // in an exercise the selected cube may have run out
+        let can_place_selected = |cubes: &CubeGrid, x, y, z| {
+            selected_cube.is_some_and(|cube| exercise.as_ref().is_none_or(|exercise| exercise.can_use(cube, cubes)))
+                && support::can_place(cubes, support_rule, x, y, z)
+        };
+        if is_mouse_button_released(MouseButton::Left) {
+            let was_click = click_position.take()
+                .is_some_and(|start| start.distance(mouse_position) < CLICK_TOLERANCE);
+            if let (true, Some((x, y, z)), Some(cube)) = (was_click, target_cell, selected_cube) {
+                if can_place_selected(&cubes, x, y, z) {
+                    history.place(&mut cubes, x, y, z, cube);
                 }
             }
         }
+        // R spins the cube under the mouse
+        if is_key_pressed(KeyCode::R) && !egui_keyboard_requested {
+            if let Some(Pick::Cube { cell: (x, y, z), .. }) = picked {
+                let rotated = cubemap.rotated(cubes.get(x, y, z).unwrap(), 1);
+                history.change(&mut cubes, x, y, z, rotated);
+            }
+        }
+        if is_mouse_button_released(MouseButton::Right) {
+            let was_click = right_click_position.take()
+                .is_some_and(|start| start.distance(mouse_position) < CLICK_TOLERANCE);
+            if let (true, Some(Pick::Cube { cell: (x, y, z), .. })) = (was_click, picked) {
+                history.remove(&mut cubes, x, y, z);
+            }
+        }
+
+        for &(view, _) in panes.iter() {
+            set_camera(if quad_view { &quad_cameras[view] } else { &camera });
+
+            draw_construction(&cubes, &cubemap, show_paving);
+            if let (Some((x, y, z)), Some(_)) = (target_cell, selected_cube) {
+                let color = if can_place_selected(&cubes, x, y, z) { ORANGE } else { RED };
+                draw_cube_wires(vec3(x as f32, y as f32, z as f32), vec3(1.0, 1.0, 1.0), color);
+            }
+            for &(x, y, z) in unsupported.iter() {
+                draw_cube_wires(vec3(x as f32, y as f32, z as f32), vec3(1.05, 1.05, 1.05), RED);
+            }
+            // only the cubes to fix are shown, the missing ones would give the solution away
+            let differences = exercise_result.as_ref()
+                .and_then(|check| check.comparison.as_ref())
+                .map_or(&[][..], |comparison| &comparison.differences);
+            for difference in differences.iter().filter(|difference| difference.found.is_some()) {
+                let position = vec3(difference.x as f32, difference.y as f32, difference.z as f32);
+                draw_cube_wires(position, vec3(1.1, 1.1, 1.1), MAGENTA);
+            }
+        }
+        if quad_view {
+            set_default_camera();
+            let (x, y, width, height) = viewport_area;
+            let top = screen_height() - (y + height) as f32;
+            let (middle_x, middle_y) = (x as f32 + 0.5 * width as f32, top + 0.5 * height as f32);
+            draw_line(middle_x, top, middle_x, top + height as f32, 2.0, DARKGRAY);
+            draw_line(x as f32, middle_y, (x + width) as f32, middle_y, 2.0, DARKGRAY);
+        }
+
+        // pictures are named after the construction file
+        if let Some(request) = export_request.take() {
+            let base_name = std::path::Path::new(&file_name).with_extension("");
+            let exports = match request {
+                ExportRequest::CurrentView => {
+                    let current_camera = if quad_view { quad_cameras[camera_view] } else { camera };
+                    vec![(base_name.with_extension("png"), current_camera)]
+                }
+                ExportRequest::Presets => [CameraView::Front, CameraView::Side, CameraView::Top, CameraView::Isometric]
+                    .iter()
+                    .map(|view| {
+                        let path = format!("{}_{}.png", base_name.display(), camera_view_file_suffix(view));
+                        (path.into(), create_camera(view, &cubes, perspective, zoom_levels[*view]))
+                    })
+                    .collect(),
+            };
+            let result: Result<(), export::ExportError> = exports.iter().try_for_each(|(path, export_camera)| {
+                export::export_png(path, export_camera, export_options, || draw_construction(&cubes, &cubemap, show_paving))
+            });
+            file_status = match result {
+                Ok(()) => tr("export.done").to_owned(),
+                Err(err) => err.message(i18n::language()),
+            };
+        }
+
+        let current_window_size = (screen_width() as i32, screen_height() as i32);
+        if current_window_size != window_size {
+            window_size = current_window_size;
+            window_resized_at = get_time();
+        }
+        let window_resized = window_size != (saved_settings.window_width, saved_settings.window_height)
+            && get_time() - window_resized_at > WINDOW_SIZE_SAVE_DELAY;
+        let settings = Settings {
+            language: chosen_language,
+            show_paving,
+            hide_builder,
+            show_projections,
+            camera_view,
+            perspective,
+            quad_view,
+            transition_duration,
+            support_rule,
+            window_width: window_size.0,
+            window_height: window_size.1,
+        };
+        if (settings_changed || window_resized || quitting) && settings != saved_settings {
+            settings::save_settings(&settings);
+            saved_settings = settings;
+        }
+        if quitting {
+            break;
+        }
 
         // Back to screen space, render some text
         egui_macroquad::draw();
@@ -246,34 +861,61 @@ async fn main() {
     }
 }
 
-fn push_top(grid: &mut CubeGrid, x: usize, y: usize, cube: Cube) {
-    for z in 0..GRID_SIZE {
-        if grid[x][y][z].is_none() {
-            grid[x][y][z] = Some(cube);
-            return;
+fn draw_construction(cubes: &CubeGrid, cubemap: &CubeMap, show_paving: bool) {
+    for x in 0..cubes.width() {
+        for y in 0..cubes.depth() {
+            if show_paving {
+                // add some cubes to be used as a "pavement"
+                let paving_color = if (x+y) % 2 == 0 {
+                    GRAY
+                } else {
+                    LIGHTGRAY
+                };
+                draw_cube(vec3(x as f32, y as f32, -0.6), vec3(1.0, 1.0, 0.2), None, paving_color);
+            }
+            for (z, cell) in cubes.column(x, y).iter().enumerate() {
+                if let Some(cube) = *cell {
+                    let rotation = Mat4::from_rotation_z(cube.quarter_turns() as f32 * std::f32::consts::FRAC_PI_2);
+                    let model_matrix = Mat4::from_translation(vec3(x as f32, y as f32, z as f32)) * rotation;
+                    let gl = unsafe { get_internal_gl().quad_gl };
+                    gl.push_model_matrix(model_matrix);
+                    draw_mesh(&cubemap[cube].mesh);
+                    gl.pop_model_matrix();
+                }
+            }
         }
     }
 }
 
-fn pop_top(grid: &mut CubeGrid, x: usize, y: usize) -> Option<Cube> {
-    for z in (0..GRID_SIZE).rev() {
-        if let Some(cube) = grid[x][y][z] {
-            grid[x as usize][y as usize][z] = None;
-            return Some(cube);
-        }
+fn support_rule_name(rule: SupportRule) -> &'static str {
+    match rule {
+        SupportRule::Gravity => tr("support.gravity"),
+        SupportRule::TouchNeighbour => tr("support.touch_neighbour"),
+        SupportRule::Free => tr("support.free"),
     }
+}
 
-    None
+fn draw_projection_views(ui: &mut egui::Ui, projections: &[(ProjectionView, projection::Projection)], cell_size: egui::Vec2) {
+    ui.horizontal(|ui| {
+        for (view, projection) in projections {
+            ui.vertical(|ui| {
+                ui.label(view.name());
+                let size = cell_size * egui::vec2(projection.columns() as f32, projection.rows() as f32);
+                let (rect, _response) = ui.allocate_exact_size(size, egui::Sense::hover());
+                draw_projection(ui, projection, rect);
+            });
+        }
+    });
 }
 
-fn draw_column(col: &CubeColumn, cubemap: &CubeMap, ui: &mut egui::Ui, rect: egui::Rect, shrink: egui::Vec2) {
+fn draw_column(col: &[Option<Cube>], cubemap: &CubeMap, ui: &mut egui::Ui, rect: egui::Rect, shrink: egui::Vec2) {
     if ui.is_rect_visible(rect) {
         // first, paint "the background"
         ui.painter().rect(rect, 0.0, egui::Color32::DARK_GRAY, egui::Stroke::default());
         // then, for each element, draw the cube, but shrink it every time we move "up"
-        for z in 0..GRID_SIZE {
-            if let Some(cube) = col[z] {
-                let mut cube_shape = cubemap[cube].egui_shape.clone();
+        for (z, cell) in col.iter().enumerate() {
+            if let Some(cube) = *cell {
+                let mut cube_shape = cubemap.top_shape(cube);
                 let shrinked = rect.shrink2(shrink * z as f32);
                 translate_scale_shape(&mut cube_shape, shrinked.min.to_vec2(), shrinked.size());
                 ui.painter().add(cube_shape);
@@ -282,11 +924,32 @@ fn draw_column(col: &CubeColumn, cubemap: &CubeMap, ui: &mut egui::Ui, rect: egu
     }
 }
 
-fn draw_drag_cube(ui: &mut egui::Ui, mut shape: egui::Shape, size: egui::Vec2) -> bool {
-    let (rect, response) = ui.allocate_at_least(size, egui::Sense::drag());
+/// `remaining` is the number of cubes left, shown in a corner when they are limited.
+/// Once there are none left the cube is faded and cannot be picked any more.
+fn draw_drag_cube(ui: &mut egui::Ui, mut shape: egui::Shape, size: egui::Vec2, selected: bool, remaining: Option<usize>) -> egui::Response {
+    let sense = if remaining == Some(0) { egui::Sense::hover() } else { egui::Sense::click_and_drag() };
+    let (rect, response) = ui.allocate_at_least(size, sense);
     translate_scale_shape(&mut shape, rect.min.to_vec2(), rect.size());
     ui.painter().add(shape);
-    response.drag_started()
+    if selected {
+        ui.painter().rect_stroke(rect.expand(1.0), 0.0, egui::Stroke::new(2.0, egui::Color32::KHAKI));
+    }
+    if let Some(remaining) = remaining {
+        if remaining == 0 {
+            ui.painter().rect_filled(rect, 0.0, egui::Color32::from_white_alpha(180));
+        }
+        let radius = 0.22 * rect.height();
+        let center = rect.right_bottom() - egui::vec2(radius, radius);
+        ui.painter().circle_filled(center, radius, egui::Color32::BLACK);
+        ui.painter().text(
+            center,
+            egui::Align2::CENTER_CENTER,
+            remaining.to_string(),
+            egui::FontId::proportional(1.4 * radius),
+            egui::Color32::WHITE,
+        );
+    }
+    response
 }
 
 fn draw_x_icon(ui: &mut egui::Ui, size: egui::Vec2) -> egui::Response {
@@ -378,54 +1041,226 @@ fn compute_diamond_corners(mut rect: egui::Rect) -> Vec<egui::Pos2> {
     ]
 }
 
-fn create_top_camera() -> Camera3D {
-    let grid_mid = 0.5 * (GRID_SIZE as f32 - 1.0);
+fn create_camera(view: &CameraView, grid: &CubeGrid, perspective: bool, zoom: f32) -> Camera3D {
+    let mut camera = match view {
+        CameraView::Front => create_front_camera(grid),
+        CameraView::Side => create_side_camera(grid),
+        CameraView::Top => create_top_camera(grid),
+        CameraView::Isometric => create_isometric_camera(grid),
+    };
+    if perspective {
+        camera = to_perspective(camera, grid);
+    }
+    let preset = camera;
+    apply_zoom(&mut camera, &preset, zoom);
+
+    camera
+}
+
+/// Zooms the camera relative to the framing of the preset: orthographic cameras frame
+/// a smaller area, perspective ones get closer to their target.
+fn apply_zoom(camera: &mut Camera3D, preset: &Camera3D, zoom: f32) {
+    match camera.projection {
+        Projection::Orthographics => camera.fovy = preset.fovy / zoom,
+        // changing the field of view would distort the construction, move closer instead
+        Projection::Perspective => {
+            let distance = preset.position.distance(preset.target) / zoom;
+            let direction = (camera.position - camera.target).normalize();
+            camera.position = camera.target + distance * direction;
+        }
+    }
+}
+
+/// Camera looking at the occupied cubes from the current direction, zoomed to frame all of
+/// them, along with the zoom it needs. An empty grid is framed by the preset.
+fn fit_camera(camera: &Camera3D, view: &CameraView, grid: &CubeGrid, perspective: bool) -> (Camera3D, f32) {
+    let forward = (camera.target - camera.position).normalize();
+    let right = forward.cross(camera.up).normalize();
+    let up = right.cross(forward);
+    let corners: Vec<Vec3> = grid.iter_cubes()
+        .flat_map(|(x, y, z, _)| {
+            let center = vec3(x as f32, y as f32, z as f32);
+            [-0.5, 0.5].iter().flat_map(move |&dx| {
+                [-0.5, 0.5].iter().flat_map(move |&dy| {
+                    [-0.5, 0.5].iter().map(move |&dz| center + vec3(dx, dy, dz))
+                })
+            })
+        })
+        .collect();
+    if corners.is_empty() {
+        return (create_camera(view, grid, perspective, 1.0), 1.0);
+    }
+
+    let span = |axis: Vec3| {
+        let values = corners.iter().map(|corner| corner.dot(axis));
+        let (min, max) = values.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+        max - min
+    };
+    let aspect = camera.aspect.unwrap_or(1.0);
+    let framed_height = span(up).max(span(right) / aspect) + FIT_MARGIN;
+    // the zoom is relative to the height framed by the orthographic preset
+    let preset_height = create_camera(view, grid, false, 1.0).fovy;
+    let zoom = (preset_height / framed_height).clamp(MIN_ZOOM, MAX_ZOOM);
+
+    let (min, max) = corners.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), corner| (min.min(*corner), max.max(*corner)),
+    );
+    let center = 0.5 * (min + max);
+    let mut goal = Camera3D {
+        position: center + (camera.position - camera.target),
+        target: center,
+        ..*camera
+    };
+    apply_zoom(&mut goal, &create_camera(view, grid, perspective, 1.0), zoom);
+
+    (goal, zoom)
+}
+
+/// Turns an orthographic preset into a perspective one looking from the same direction,
+/// far enough from the grid to frame the same area.
+fn to_perspective(camera: Camera3D, grid: &CubeGrid) -> Camera3D {
+    let grid_mid = grid.center();
+    let grid_max = grid.width().max(grid.depth()).max(grid.height()) as f32;
+    let direction = (camera.position - camera.target).normalize();
+    // the framed area is measured on the plane through the middle of the grid
+    let distance = 0.5 * camera.fovy / (0.5 * PERSPECTIVE_FOVY).tan() + 0.5 * grid_max;
     Camera3D {
-        position: vec3(grid_mid, grid_mid, 10.0),
+        position: grid_mid + distance * direction,
+        target: grid_mid,
+        projection: Projection::Perspective,
+        fovy: PERSPECTIVE_FOVY,
+        ..camera
+    }
+}
+
+fn create_top_camera(grid: &CubeGrid) -> Camera3D {
+    let grid_mid = grid.center();
+    Camera3D {
+        position: vec3(grid_mid.x, grid_mid.y, grid.height() as f32 + 10.0),
         up: vec3(-1.0, 0.0, 0.0),
         projection: Projection::Orthographics,
-        target: vec3(grid_mid, grid_mid, 0.0),
-        fovy: 5.0,
+        target: vec3(grid_mid.x, grid_mid.y, 0.0),
+        fovy: grid.width().max(grid.depth()) as f32 + 1.0,
         ..Default::default()
     }
 }
 
-fn create_front_camera() -> Camera3D {
-    let grid_mid = 0.5 * (GRID_SIZE as f32 - 1.0);
+fn create_front_camera(grid: &CubeGrid) -> Camera3D {
+    let grid_mid = grid.center();
     Camera3D {
-        position: vec3(10.0, grid_mid, grid_mid),
+        position: vec3(grid.width() as f32 + 10.0, grid_mid.y, grid_mid.z),
         up: vec3(0.0, 0.0, 1.0),
         projection: Projection::Orthographics,
-        target: vec3(0.0, grid_mid, grid_mid),
-        fovy: 5.0,
+        target: vec3(0.0, grid_mid.y, grid_mid.z),
+        fovy: grid.depth().max(grid.height()) as f32 + 1.0,
         ..Default::default()
     }
 }
 
-fn create_side_camera() -> Camera3D {
-    let grid_mid = 0.5 * (GRID_SIZE as f32 - 1.0);
+fn create_side_camera(grid: &CubeGrid) -> Camera3D {
+    let grid_mid = grid.center();
     Camera3D {
-        position: vec3(grid_mid, 10.0, grid_mid),
+        position: vec3(grid_mid.x, grid.depth() as f32 + 10.0, grid_mid.z),
         up: vec3(0.0, 0.0, 1.0),
         projection: Projection::Orthographics,
-        target: vec3(grid_mid, 0.0, grid_mid),
-        fovy: 5.0,
+        target: vec3(grid_mid.x, 0.0, grid_mid.z),
+        fovy: grid.width().max(grid.height()) as f32 + 1.0,
         ..Default::default()
     }
 }
 
-fn create_isometric_camera() -> Camera3D {
-    let grid_mid = 0.5 * (GRID_SIZE as f32 - 1.0);
+fn create_isometric_camera(grid: &CubeGrid) -> Camera3D {
+    let grid_mid = grid.center();
+    let grid_max = grid.width().max(grid.depth()).max(grid.height()) as f32;
     Camera3D {
-        position: vec3(grid_mid + 10.0, grid_mid + 7.5, grid_mid + 5.0),
+        position: grid_mid + vec3(10.0, 7.5, 5.0),
         up: vec3(0.0, 0.0, 1.0),
         projection: Projection::Orthographics,
-        target: vec3(grid_mid, grid_mid, grid_mid),
-        fovy: 6.0,
+        target: grid_mid,
+        fovy: 1.5 * grid_max,
         ..Default::default()
     }
 }
 
+/// Added to the name of the construction to name the picture exported for the view.
+fn camera_view_file_suffix(view: &CameraView) -> &'static str {
+    match view {
+        CameraView::Front => tr("export.suffix.front"),
+        CameraView::Side => tr("export.suffix.side"),
+        CameraView::Top => tr("export.suffix.top"),
+        CameraView::Isometric => tr("export.suffix.isometric"),
+    }
+}
+
+/// Splits the 3D area in four panes: the top view is above the front one, so that they
+/// share the horizontal axis, and the side view is next to the front one.
+fn split_viewport((x, y, width, height): (i32, i32, i32, i32)) -> Vec<(CameraView, (i32, i32, i32, i32))> {
+    // viewports are expressed from the bottom of the screen
+    let (half_width, half_height) = (width / 2, height / 2);
+    vec![
+        (CameraView::Top, (x, y + half_height, half_width, height - half_height)),
+        (CameraView::Isometric, (x + half_width, y + half_height, width - half_width, height - half_height)),
+        (CameraView::Front, (x, y, half_width, half_height)),
+        (CameraView::Side, (x + half_width, y, width - half_width, half_height)),
+    ]
+}
+
+fn viewport_contains((x, y, width, height): (i32, i32, i32, i32), screen_position: Vec2) -> bool {
+    let top = screen_height() - (y + height) as f32;
+    screen_position.x >= x as f32 && screen_position.x < (x + width) as f32
+        && screen_position.y >= top && screen_position.y < top + height as f32
+}
+
+/// Drags the camera: the isometric view orbits around `center` unless `panning`,
+/// the other views always move parallel to their plane.
+fn move_camera(camera: &mut Camera3D, view: CameraView, center: Vec3, mouse_delta: Vec2, panning: bool) {
+    if !panning && view == CameraView::Isometric {
+        orbit_camera(camera, center, -ORBIT_SPEED * mouse_delta.x, ORBIT_SPEED * mouse_delta.y);
+        return;
+    }
+    // move camera, something different can happen depending on the active view
+    let scale = 0.02;
+    match view {
+        CameraView::Top => {
+            camera.target.x -= scale * mouse_delta.y;
+            camera.position.x -= scale * mouse_delta.y;
+            camera.target.y -= scale * mouse_delta.x;
+            camera.position.y -= scale * mouse_delta.x;
+        }
+        CameraView::Front => {
+            camera.target.z += scale * mouse_delta.y;
+            camera.position.z += scale * mouse_delta.y;
+            camera.target.y -= scale * mouse_delta.x;
+            camera.position.y -= scale * mouse_delta.x;
+        }
+        CameraView::Side => {
+            camera.target.z += scale * mouse_delta.y;
+            camera.position.z += scale * mouse_delta.y;
+            camera.target.x -= scale * mouse_delta.x;
+            camera.position.x -= scale * mouse_delta.x;
+        }
+        CameraView::Isometric => {
+            let camera_dir = camera.target - camera.position;
+            let camera_left = camera.up.cross(camera_dir).normalize();
+            camera.target += scale * (mouse_delta.x*camera_left + mouse_delta.y * camera.up);
+            camera.position += scale * (mouse_delta.x*camera_left + mouse_delta.y * camera.up);
+        }
+    }
+}
+
+/// Turns the camera around the vertical axis and then up or down, always around `center`.
+/// The pitch is clamped so that the camera never flips over the construction.
+fn orbit_camera(camera: &mut Camera3D, center: Vec3, yaw: f32, pitch: f32) {
+    let direction = (camera.target - camera.position).normalize();
+    let current_pitch = (-direction.z).asin();
+    let pitch = (current_pitch + pitch).clamp(MIN_ORBIT_PITCH, MAX_ORBIT_PITCH) - current_pitch;
+    let right = direction.cross(vec3(0.0, 0.0, 1.0)).normalize();
+    let rotation = Quat::from_rotation_z(yaw) * Quat::from_axis_angle(right, -pitch);
+    camera.position = center + rotation * (camera.position - center);
+    camera.target = center + rotation * (camera.target - center);
+}
+
 fn translate_scale_shape(shape: &mut egui::Shape, translation: egui::Vec2, size: egui::Vec2) {
     match shape {
         egui::Shape::Rect(ref mut rect_shape) => {
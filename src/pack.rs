@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::construction::{self, ConstructionError};
+use crate::cubes::{Cube, CubeMap};
+use crate::equivalence::Equivalence;
+use crate::grid::CubeGrid;
+use crate::i18n::{tr_in, Language};
+use crate::projection::ProjectionView;
+use crate::storage;
+
+/// Version written in every pack file, to be bumped whenever its layout changes.
+pub const PACK_FORMAT_VERSION: u32 = 1;
+
+const PROGRESS_NAME: &str = "progress";
+
+/// A pack as written by the teacher. The targets are construction files saved by the application,
+/// so that they can be built and checked with it before being given to the students.
+#[derive(Deserialize)]
+struct PackFile {
+    version: u32,
+    name: String,
+    levels: Vec<LevelFile>,
+}
+
+#[derive(Deserialize)]
+struct LevelFile {
+    #[serde(default)]
+    title: String,
+    /// Path of the construction file, relative to the pack file
+    target: String,
+    /// Names of the palette cubes the student can use; every cube when missing,
+    /// or just the ones of the inventory if there is one
+    #[serde(default)]
+    cubes: Option<Vec<String>>,
+    /// How many cubes of each kind the student has. Kinds named in `cubes` but not listed here
+    /// have no limit; without `cubes` only the listed kinds can be used
+    #[serde(default)]
+    inventory: Option<BTreeMap<String, usize>>,
+    #[serde(default = "all_views")]
+    views: Vec<ProjectionView>,
+    #[serde(default)]
+    hide_schema: bool,
+    /// Whether the target is also accepted moved or turned; just as it is when missing
+    #[serde(default)]
+    equivalence: Option<Equivalence>,
+}
+
+fn all_views() -> Vec<ProjectionView> {
+    ProjectionView::ALL.to_vec()
+}
+
+/// Sequence of exercises to be solved in order: each level is unlocked by solving the previous one.
+pub struct Pack {
+    pub name: String,
+    pub levels: Vec<Level>,
+}
+
+pub struct Level {
+    pub title: String,
+    pub target: CubeGrid,
+    /// Cubes of the palette that can be used, or None for the whole palette
+    pub cubes: Option<Vec<Cube>>,
+    /// Number of cubes of the listed kinds the student has, or None when there is no limit
+    pub inventory: Option<Vec<(Cube, usize)>>,
+    /// Views of the target shown to the student, the only ones that are checked
+    pub views: Vec<ProjectionView>,
+    pub hide_schema: bool,
+    /// How the target may be moved and still be accepted, None when only its views count
+    pub equivalence: Option<Equivalence>,
+}
+
+#[derive(Debug)]
+pub enum PackError {
+    Io(std::io::Error),
+    Parse(ron::Error),
+    UnsupportedVersion(u32),
+    NoLevels,
+    /// The level, counting from 1, whose target could not be loaded
+    Target { level: usize, error: ConstructionError },
+    UnknownCube(String),
+}
+
+impl PackError {
+    /// Like `ConstructionError::message`.
+    pub fn message(&self, language: Language) -> String {
+        let tr = |key| tr_in(language, key);
+        match self {
+            PackError::Io(err) => tr("error.io").replace("{error}", &err.to_string()),
+            PackError::Parse(err) => tr("error.pack_parse").replace("{error}", &err.to_string()),
+            PackError::UnsupportedVersion(version) => tr("error.pack_version").replace("{version}", &version.to_string()),
+            PackError::NoLevels => tr("error.no_levels").to_owned(),
+            PackError::Target { level, error } => tr("error.level")
+                .replace("{number}", &level.to_string())
+                .replace("{error}", &error.message(language)),
+            PackError::UnknownCube(name) => tr("error.unknown_cube").replace("{name}", name),
+        }
+    }
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Language::English))
+    }
+}
+
+impl From<std::io::Error> for PackError {
+    fn from(err: std::io::Error) -> Self {
+        PackError::Io(err)
+    }
+}
+
+impl From<ron::Error> for PackError {
+    fn from(err: ron::Error) -> Self {
+        PackError::Parse(err)
+    }
+}
+
+pub fn load_pack(path: impl AsRef<Path>, cubemap: &CubeMap) -> Result<Pack, PackError> {
+    let path = path.as_ref();
+    let file: PackFile = ron::from_str(&std::fs::read_to_string(path)?)?;
+    if file.version != PACK_FORMAT_VERSION {
+        return Err(PackError::UnsupportedVersion(file.version));
+    }
+    if file.levels.is_empty() {
+        return Err(PackError::NoLevels);
+    }
+
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut levels = Vec::with_capacity(file.levels.len());
+    for (index, level) in file.levels.into_iter().enumerate() {
+        let target = construction::load_from_file(directory.join(&level.target), cubemap)
+            .map_err(|error| PackError::Target { level: index + 1, error })?;
+        let find = |name: String| cubemap.find(&name).ok_or(PackError::UnknownCube(name));
+        let inventory = match level.inventory {
+            Some(counts) => Some(counts.into_iter()
+                .map(|(name, count)| find(name).map(|cube| (cube, count)))
+                .collect::<Result<Vec<_>, _>>()?),
+            None => None,
+        };
+        let cubes = match (level.cubes, &inventory) {
+            (Some(names), _) => Some(names.into_iter().map(find).collect::<Result<Vec<_>, _>>()?),
+            (None, Some(inventory)) => Some(inventory.iter().map(|&(cube, _)| cube).collect()),
+            (None, None) => None,
+        };
+        levels.push(Level {
+            title: level.title,
+            target,
+            cubes,
+            inventory,
+            views: level.views,
+            hide_schema: level.hide_schema,
+            equivalence: level.equivalence,
+        });
+    }
+
+    Ok(Pack { name: file.name, levels })
+}
+
+/// How far every student got in every pack, kept on the computer the students share.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    /// The student who used the application last
+    pub student: String,
+    /// Number of levels solved, by pack name and then by student
+    solved: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl Progress {
+    /// Number of levels the current student solved in the pack.
+    pub fn solved_levels(&self, pack: &Pack) -> usize {
+        self.solved.get(&pack.name)
+            .and_then(|students| students.get(&self.student))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Levels are unlocked up to the first one that has not been solved yet.
+    pub fn is_unlocked(&self, pack: &Pack, level: usize) -> bool {
+        level <= self.solved_levels(pack)
+    }
+
+    pub fn mark_solved(&mut self, pack: &Pack, level: usize) {
+        let solved = self.solved.entry(pack.name.clone())
+            .or_default()
+            .entry(self.student.clone())
+            .or_default();
+        *solved = (*solved).max(level + 1);
+    }
+}
+
+/// Failures are only logged: the students can go on, they will just have to solve the levels again.
+pub fn load_progress() -> Progress {
+    storage::load_ron(PROGRESS_NAME)
+}
+
+pub fn save_progress(progress: &Progress) {
+    storage::save_ron(PROGRESS_NAME, progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubes::test_cubemap;
+
+    const EXAMPLE_PACK: &str = "packs/primi_passi.ron";
+
+    /// Writes a pack in the temporary directory, under a name of its own for every test.
+    fn temporary_pack(name: &str, text: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("lacu_test_{}.ron", name));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn example_pack_loads() {
+        let cubemap = test_cubemap();
+        let pack = load_pack(EXAMPLE_PACK, &cubemap).unwrap();
+        assert_eq!(pack.name, "Primi passi");
+        let titles: Vec<&str> = pack.levels.iter().map(|level| level.title.as_str()).collect();
+        assert_eq!(titles, ["Una torre", "Un muretto", "A occhi chiusi"]);
+
+        let [tower, wall, stairs] = &pack.levels[..] else { panic!("the pack has three levels") };
+        assert_eq!(tower.equivalence, Some(Equivalence::Rotation));
+        assert_eq!(tower.target.iter_cubes().count(), 3);
+        assert_eq!(wall.views, [ProjectionView::Front, ProjectionView::Top]);
+        assert_eq!(wall.equivalence, None);
+        assert!(stairs.hide_schema);
+        assert_eq!(stairs.views, ProjectionView::ALL);
+        assert_eq!(stairs.cubes, None);
+    }
+
+    #[test]
+    fn inventory_limits_the_cubes() {
+        let cubemap = test_cubemap();
+        let pack = load_pack(EXAMPLE_PACK, &cubemap).unwrap();
+        let find = |name| cubemap.find(name).unwrap();
+
+        // without a list of cubes only the ones of the inventory can be used
+        let tower = &pack.levels[0];
+        assert_eq!(tower.inventory, Some(vec![(find("Blue"), 1), (find("Red"), 2)]));
+        assert_eq!(tower.cubes, Some(vec![find("Blue"), find("Red")]));
+        let wall = &pack.levels[1];
+        assert_eq!(wall.cubes, Some(vec![find("Yellow"), find("RedWhite"), find("Green")]));
+        assert_eq!(wall.inventory, None);
+
+        // the target is joined to the temporary directory, so it has to be a full path
+        let target = std::env::current_dir().unwrap().join("packs/primi_passi/torre.ron");
+        let text = format!(r#"(version: 1, name: "Odd", levels: [(target: {:?}, inventory: Some({{"Purple": 1}}))])"#, target);
+        let path = temporary_pack("unknown_cube", &text);
+        assert!(matches!(load_pack(&path, &cubemap), Err(PackError::UnknownCube(name)) if name == "Purple"));
+    }
+
+    #[test]
+    fn broken_packs_are_rejected() {
+        let cubemap = test_cubemap();
+        assert!(matches!(load_pack("packs/missing.ron", &cubemap), Err(PackError::Io(_))));
+
+        let path = temporary_pack("no_levels", r#"(version: 1, name: "Empty", levels: [])"#);
+        assert!(matches!(load_pack(&path, &cubemap), Err(PackError::NoLevels)));
+
+        let path = temporary_pack("future", r#"(version: 2, name: "Future", levels: [])"#);
+        assert!(matches!(load_pack(&path, &cubemap), Err(PackError::UnsupportedVersion(2))));
+
+        let path = temporary_pack("missing_target", r#"(version: 1, name: "Lost", levels: [(target: "lacu_test_nowhere.ron")])"#);
+        assert!(matches!(load_pack(&path, &cubemap), Err(PackError::Target { level: 1, error: ConstructionError::Io(_) })));
+    }
+
+    #[test]
+    fn levels_unlock_one_by_one_for_each_student() {
+        let pack = Pack { name: "Primi passi".to_owned(), levels: Vec::new() };
+        let mut progress = Progress { student: "Anna".to_owned(), ..Progress::default() };
+        assert!(progress.is_unlocked(&pack, 0));
+        assert!(!progress.is_unlocked(&pack, 1));
+
+        progress.mark_solved(&pack, 0);
+        progress.mark_solved(&pack, 1);
+        assert_eq!(progress.solved_levels(&pack), 2);
+        assert!(progress.is_unlocked(&pack, 2));
+        assert!(!progress.is_unlocked(&pack, 3));
+        // solving an earlier level again does not lock the later ones
+        progress.mark_solved(&pack, 0);
+        assert_eq!(progress.solved_levels(&pack), 2);
+
+        progress.student = "Marco".to_owned();
+        assert_eq!(progress.solved_levels(&pack), 0);
+        let other_pack = Pack { name: "Altro".to_owned(), levels: Vec::new() };
+        progress.student = "Anna".to_owned();
+        assert_eq!(progress.solved_levels(&other_pack), 0);
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::assets;
+use crate::cubes::{CubeDescription, Face, FaceColors};
+
+/// Palette read at startup, so that every school can use the colors of its own cube sets.
+pub const PALETTE_PATH: &str = "config/palette.ron";
+const EMBEDDED_PALETTE: &str = include_str!("../config/palette.ron");
+
+type Rgb = (u8, u8, u8);
+
+fn color(rgb: Rgb) -> egui::Color32 {
+    egui::Color32::from_rgb(rgb.0, rgb.1, rgb.2)
+}
+
+#[derive(Deserialize)]
+struct PaletteFile {
+    cubes: Vec<PaletteEntry>,
+}
+
+#[derive(Deserialize)]
+struct PaletteEntry {
+    name: String,
+    pattern: CubePattern,
+}
+
+#[derive(Deserialize)]
+enum CubePattern {
+    Solid(Rgb),
+    Split {
+        north: Rgb,
+        south: Rgb,
+        #[serde(default)]
+        rotation: usize,
+    },
+    Faces {
+        pos_x: FacePattern,
+        neg_x: FacePattern,
+        pos_y: FacePattern,
+        neg_y: FacePattern,
+        top: FacePattern,
+        bottom: FacePattern,
+    },
+}
+
+#[derive(Deserialize)]
+enum FacePattern {
+    Solid(Rgb),
+    MainDiagonal { top_right: Rgb, bottom_left: Rgb },
+    AntiDiagonal { top_left: Rgb, bottom_right: Rgb },
+}
+
+impl FacePattern {
+    fn colors(&self) -> FaceColors {
+        match *self {
+            FacePattern::Solid(rgb) => FaceColors::solid(color(rgb)),
+            FacePattern::MainDiagonal { top_right, bottom_left } => {
+                FaceColors::split_main_diagonal(color(top_right), color(bottom_left))
+            }
+            FacePattern::AntiDiagonal { top_left, bottom_right } => {
+                FaceColors::split_anti_diagonal(color(top_left), color(bottom_right))
+            }
+        }
+    }
+}
+
+impl CubePattern {
+    fn description(&self) -> CubeDescription {
+        match self {
+            CubePattern::Solid(rgb) => CubeDescription::solid(color(*rgb)),
+            CubePattern::Split { north, south, rotation } => {
+                CubeDescription::split(color(*north), color(*south)).rotated_z(*rotation)
+            }
+            CubePattern::Faces { pos_x, neg_x, pos_y, neg_y, top, bottom } => {
+                CubeDescription::new(enum_map! {
+                    Face::PosX => pos_x.colors(),
+                    Face::NegX => neg_x.colors(),
+                    Face::PosY => pos_y.colors(),
+                    Face::NegY => neg_y.colors(),
+                    Face::Top => top.colors(),
+                    Face::Bottom => bottom.colors(),
+                })
+            }
+        }
+    }
+}
+
+/// A kind of cube that can be placed in the grid.
+pub struct PaletteCube {
+    pub name: String,
+    pub description: CubeDescription,
+}
+
+#[derive(Debug)]
+pub enum PaletteError {
+    Parse(ron::Error),
+    Empty,
+    DuplicateName(String),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::Parse(err) => write!(f, "invalid palette: {}", err),
+            PaletteError::Empty => write!(f, "the palette has no cubes"),
+            PaletteError::DuplicateName(name) => write!(f, "the palette has two cubes named {}", name),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<PaletteCube>, PaletteError> {
+    let file: PaletteFile = ron::from_str(text).map_err(PaletteError::Parse)?;
+    if file.cubes.is_empty() {
+        return Err(PaletteError::Empty);
+    }
+    let mut names = HashSet::new();
+    for entry in file.cubes.iter() {
+        if !names.insert(entry.name.as_str()) {
+            return Err(PaletteError::DuplicateName(entry.name.clone()));
+        }
+    }
+
+    Ok(file.cubes.iter()
+        .map(|entry| PaletteCube {
+            name: entry.name.clone(),
+            description: entry.pattern.description(),
+        })
+        .collect())
+}
+
+pub async fn load_palette() -> Vec<PaletteCube> {
+    assets::load_or_embedded(PALETTE_PATH, EMBEDDED_PALETTE.as_bytes(), parse_bytes).await
+}
+
+pub fn read_palette() -> Vec<PaletteCube> {
+    assets::read_or_embedded(PALETTE_PATH, EMBEDDED_PALETTE.as_bytes(), parse_bytes)
+}
+
+fn parse_bytes(data: &[u8]) -> Result<Vec<PaletteCube>, PaletteError> {
+    parse(&String::from_utf8_lossy(data))
+}
@@ -0,0 +1,117 @@
+use macroquad::prelude::*;
+
+use crate::grid::CubeGrid;
+
+/// What lies under the mouse pointer in the 3D view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pick {
+    /// A face of the cube in the given cell; `normal` is the direction the face looks at.
+    Cube { cell: (usize, usize, usize), normal: (i32, i32, i32) },
+    /// The floor of the grid, below the given column.
+    Floor { x: usize, y: usize },
+}
+
+impl Pick {
+    /// The cell a new cube would be placed in: the one next to the picked face,
+    /// or the bottom of the picked column. None if that cell is outside the grid.
+    pub fn target_cell(&self, grid: &CubeGrid) -> Option<(usize, usize, usize)> {
+        let (x, y, z) = match *self {
+            Pick::Cube { cell, normal } => (
+                cell.0 as i32 + normal.0,
+                cell.1 as i32 + normal.1,
+                cell.2 as i32 + normal.2,
+            ),
+            Pick::Floor { x, y } => (x as i32, y as i32, 0),
+        };
+        if x < 0 || y < 0 || z < 0 || !grid.contains(x as usize, y as usize, z as usize) {
+            return None;
+        }
+        let cell = (x as usize, y as usize, z as usize);
+
+        grid.get(cell.0, cell.1, cell.2).is_none().then_some(cell)
+    }
+}
+
+/// Ray going from the camera into the scene, through the given point of the screen.
+/// Returns None if the point is outside the camera viewport.
+pub fn screen_ray(camera: &Camera3D, screen_position: Vec2) -> Option<(Vec3, Vec3)> {
+    let (x, y, width, height) = camera.viewport
+        .unwrap_or((0, 0, screen_width() as i32, screen_height() as i32));
+    // the viewport is expressed from the bottom of the screen, the mouse from the top
+    let top = screen_height() - (y + height) as f32;
+    let ndc = vec2(
+        2.0 * (screen_position.x - x as f32) / width as f32 - 1.0,
+        1.0 - 2.0 * (screen_position.y - top) / height as f32,
+    );
+    if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+        return None;
+    }
+    let inverse = camera.matrix().inverse();
+    let near = inverse.project_point3(vec3(ndc.x, ndc.y, -1.0));
+    let far = inverse.project_point3(vec3(ndc.x, ndc.y, 1.0));
+
+    Some((near, (far - near).normalize()))
+}
+
+/// Finds the closest cube face hit by the ray, falling back on the floor of the grid.
+pub fn pick(grid: &CubeGrid, origin: Vec3, direction: Vec3) -> Option<Pick> {
+    let mut closest: Option<(f32, Pick)> = None;
+    for (x, y, z, _cube) in grid.iter_cubes() {
+        let center = vec3(x as f32, y as f32, z as f32);
+        if let Some((distance, normal)) = ray_box_intersection(origin, direction, center) {
+            if closest.is_none_or(|(closest_distance, _)| distance < closest_distance) {
+                closest = Some((distance, Pick::Cube { cell: (x, y, z), normal }));
+            }
+        }
+    }
+    if let Some((_, pick)) = closest {
+        return Some(pick);
+    }
+
+    // the floor is the top of the paving, right below the first layer of cubes
+    if direction.z.abs() < f32::EPSILON {
+        return None;
+    }
+    let distance = (-0.5 - origin.z) / direction.z;
+    if distance < 0.0 {
+        return None;
+    }
+    let point = origin + distance * direction;
+    let (x, y) = (point.x.round(), point.y.round());
+    if x < 0.0 || y < 0.0 || x >= grid.width() as f32 || y >= grid.depth() as f32 {
+        return None;
+    }
+
+    Some(Pick::Floor { x: x as usize, y: y as usize })
+}
+
+/// Slab test against the unit cube centered in `center`. Returns the distance along
+/// the ray and the normal of the face where the ray enters the cube.
+fn ray_box_intersection(origin: Vec3, direction: Vec3, center: Vec3) -> Option<(f32, (i32, i32, i32))> {
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = (0, 0, 0);
+    for axis in 0..3 {
+        let (o, d) = (origin[axis] - center[axis], direction[axis]);
+        if d.abs() < f32::EPSILON {
+            if o.abs() > 0.5 {
+                return None;
+            }
+            continue;
+        }
+        let (near, far) = ((-0.5 * d.signum() - o) / d, (0.5 * d.signum() - o) / d);
+        if near > enter {
+            enter = near;
+            // the ray enters from the side facing against its direction
+            let sign = -d.signum() as i32;
+            normal = match axis {
+                0 => (sign, 0, 0),
+                1 => (0, sign, 0),
+                _ => (0, 0, sign),
+            };
+        }
+        exit = exit.min(far);
+    }
+
+    (enter <= exit && exit >= 0.0).then_some((enter, normal))
+}
@@ -0,0 +1,337 @@
+use std::fmt;
+
+use macroquad::prelude::Vec3;
+
+/// Minimal reader for the PLY files exported by Blender: it understands the ASCII and the
+/// binary little-endian encodings, and only keeps vertex positions, vertex colors and faces.
+pub struct PlyModel {
+    pub vertices: Vec<PlyVertex>,
+    /// Faces are split in triangles while loading.
+    pub triangles: Vec<[usize; 3]>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlyVertex {
+    pub position: Vec3,
+    /// RGBA color; white if the file has no vertex colors
+    pub color: [u8; 4],
+}
+
+/// Meshes are drawn with 16 bit indices.
+pub const MAX_MESH_VERTICES: usize = u16::MAX as usize + 1;
+
+#[derive(Debug)]
+pub enum PlyError {
+    NotPly,
+    UnsupportedFormat(String),
+    InvalidHeader(String),
+    UnexpectedEnd,
+    InvalidValue(String),
+    InvalidIndex(usize),
+    /// The painted mesh would need more vertices than 16 bit indices can address
+    TooManyVertices,
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::NotPly => write!(f, "not a PLY file"),
+            PlyError::UnsupportedFormat(format) => write!(f, "unsupported PLY format: {}", format),
+            PlyError::InvalidHeader(line) => write!(f, "invalid PLY header line: {}", line),
+            PlyError::UnexpectedEnd => write!(f, "unexpected end of PLY data"),
+            PlyError::InvalidValue(value) => write!(f, "invalid PLY value: {}", value),
+            PlyError::InvalidIndex(index) => write!(f, "face uses missing vertex {}", index),
+            PlyError::TooManyVertices => write!(f, "the model is too detailed, meshes can have at most {} vertices", MAX_MESH_VERTICES),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Clone, Copy)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(ScalarType::I8),
+            "uchar" | "uint8" => Some(ScalarType::U8),
+            "short" | "int16" => Some(ScalarType::I16),
+            "ushort" | "uint16" => Some(ScalarType::U16),
+            "int" | "int32" => Some(ScalarType::I32),
+            "uint" | "uint32" => Some(ScalarType::U32),
+            "float" | "float32" => Some(ScalarType::F32),
+            "double" | "float64" => Some(ScalarType::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+}
+
+enum PropertyType {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+struct Property {
+    name: String,
+    kind: PropertyType,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads the values one by one, hiding the differences between the two encodings.
+struct ValueReader<'a> {
+    encoding: Encoding,
+    data: &'a [u8],
+    position: usize,
+}
+
+impl ValueReader<'_> {
+    fn read(&mut self, kind: ScalarType) -> Result<f64, PlyError> {
+        match self.encoding {
+            Encoding::Ascii => self.read_ascii(),
+            Encoding::BinaryLittleEndian => self.read_binary(kind),
+        }
+    }
+
+    fn read_ascii(&mut self) -> Result<f64, PlyError> {
+        let rest = &self.data[self.position..];
+        let start = rest.iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .ok_or(PlyError::UnexpectedEnd)?;
+        let length = rest[start..].iter()
+            .position(|byte| byte.is_ascii_whitespace())
+            .unwrap_or(rest.len() - start);
+        self.position += start + length;
+        let token = String::from_utf8_lossy(&rest[start..start + length]);
+        token.parse().map_err(|_| PlyError::InvalidValue(token.into_owned()))
+    }
+
+    fn read_binary(&mut self, kind: ScalarType) -> Result<f64, PlyError> {
+        let bytes = self.data
+            .get(self.position..self.position + kind.size())
+            .ok_or(PlyError::UnexpectedEnd)?;
+        self.position += kind.size();
+        let value = match kind {
+            ScalarType::I8 => bytes[0] as i8 as f64,
+            ScalarType::U8 => bytes[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::F64 => {
+                let mut array = [0; 8];
+                array.copy_from_slice(bytes);
+                f64::from_le_bytes(array)
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+pub fn parse(data: &[u8]) -> Result<PlyModel, PlyError> {
+    let (encoding, elements, body_start) = parse_header(data)?;
+    let mut reader = ValueReader {
+        encoding,
+        data,
+        position: body_start,
+    };
+
+    let mut model = PlyModel {
+        vertices: Vec::new(),
+        triangles: Vec::new(),
+    };
+    for element in elements.iter() {
+        for _ in 0..element.count {
+            let mut position = Vec3::ZERO;
+            let mut color = [255; 4];
+            let mut indices = Vec::new();
+            for property in element.properties.iter() {
+                match property.kind {
+                    PropertyType::Scalar(kind) => {
+                        let value = reader.read(kind)?;
+                        match property.name.as_str() {
+                            "x" => position.x = value as f32,
+                            "y" => position.y = value as f32,
+                            "z" => position.z = value as f32,
+                            "red" => color[0] = value as u8,
+                            "green" => color[1] = value as u8,
+                            "blue" => color[2] = value as u8,
+                            "alpha" => color[3] = value as u8,
+                            _ => {}
+                        }
+                    }
+                    PropertyType::List { count, item } => {
+                        let length = reader.read(count)? as usize;
+                        for _ in 0..length {
+                            indices.push(reader.read(item)? as usize);
+                        }
+                    }
+                }
+            }
+            match element.name.as_str() {
+                "vertex" => model.vertices.push(PlyVertex { position, color }),
+                // split the polygon as a triangle fan
+                "face" => {
+                    for i in 1..indices.len().saturating_sub(1) {
+                        model.triangles.push([indices[0], indices[i], indices[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(&index) = model.triangles.iter().flatten().find(|&&index| index >= model.vertices.len()) {
+        return Err(PlyError::InvalidIndex(index));
+    }
+
+    Ok(model)
+}
+
+fn parse_header(data: &[u8]) -> Result<(Encoding, Vec<Element>, usize), PlyError> {
+    const END_HEADER: &[u8] = b"end_header";
+    let header_end = data.windows(END_HEADER.len())
+        .position(|window| window == END_HEADER)
+        .ok_or(PlyError::NotPly)?;
+    // the body starts right after the newline that ends the header
+    let body_start = data[header_end..].iter()
+        .position(|byte| *byte == b'\n')
+        .map(|offset| header_end + offset + 1)
+        .unwrap_or(data.len());
+    let header = String::from_utf8_lossy(&data[..header_end]);
+
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(PlyError::NotPly);
+    }
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let invalid = || PlyError::InvalidHeader(line.to_owned());
+        match words.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", "ascii", _] => encoding = Some(Encoding::Ascii),
+            ["format", "binary_little_endian", _] => encoding = Some(Encoding::BinaryLittleEndian),
+            ["format", format, ..] => return Err(PlyError::UnsupportedFormat(format.to_string())),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid())?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let kind = PropertyType::List {
+                    count: ScalarType::parse(count).ok_or_else(invalid)?,
+                    item: ScalarType::parse(item).ok_or_else(invalid)?,
+                };
+                let element = elements.last_mut().ok_or_else(invalid)?;
+                element.properties.push(Property { name: name.to_string(), kind });
+            }
+            ["property", kind, name] => {
+                let kind = PropertyType::Scalar(ScalarType::parse(kind).ok_or_else(invalid)?);
+                let element = elements.last_mut().ok_or_else(invalid)?;
+                element.properties.push(Property { name: name.to_string(), kind });
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    let encoding = encoding.ok_or(PlyError::NotPly)?;
+
+    Ok((encoding, elements, body_start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "\
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar uint vertex_indices
+end_header
+";
+
+    fn check_square(model: &PlyModel) {
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.vertices[2].position, Vec3::new(1.0, 1.0, 0.5));
+        assert_eq!(model.vertices[1].color, [255, 0, 0, 255]);
+        // the square is split in two triangles sharing the first vertex
+        assert_eq!(model.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn ascii_model() {
+        let body = "\
+0 0 0.5 0 0 0
+1 0 0.5 255 0 0
+1 1 0.5 0 255 0
+0 1 0.5 0 0 255
+4 0 1 2 3
+";
+        let data = format!("ply\nformat ascii 1.0\ncomment made by hand\n{}{}", HEADER, body);
+        check_square(&parse(data.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn binary_model() {
+        let mut data = format!("ply\nformat binary_little_endian 1.0\n{}", HEADER).into_bytes();
+        let vertices = [
+            ([0.0f32, 0.0, 0.5], [0, 0, 0]),
+            ([1.0, 0.0, 0.5], [255, 0, 0]),
+            ([1.0, 1.0, 0.5], [0, 255, 0]),
+            ([0.0, 1.0, 0.5], [0, 0, 255]),
+        ];
+        for (position, color) in vertices {
+            for coordinate in position {
+                data.extend(coordinate.to_le_bytes());
+            }
+            data.extend(color);
+        }
+        data.push(4);
+        for index in 0u32..4 {
+            data.extend(index.to_le_bytes());
+        }
+        check_square(&parse(&data).unwrap());
+    }
+
+    #[test]
+    fn missing_vertices_are_rejected() {
+        let data = format!("ply\nformat ascii 1.0\n{}0 0 0 0 0 0\n", HEADER);
+        assert!(matches!(parse(data.as_bytes()), Err(PlyError::UnexpectedEnd)));
+    }
+}
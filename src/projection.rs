@@ -0,0 +1,130 @@
+use serde::Deserialize;
+
+use crate::cubes::{CubeMap, Face, FaceColors};
+use crate::grid::CubeGrid;
+use crate::i18n::tr;
+
+/// Directions from which a construction can be projected, matching the orthographic cameras.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum ProjectionView {
+    /// Looking from +x: rows follow z from the top down, columns follow y
+    Front,
+    /// Looking from +y: rows follow z from the top down, columns follow x backwards
+    Side,
+    /// Looking from +z: rows follow x, columns follow y, exactly like the builder schema
+    Top,
+}
+
+impl ProjectionView {
+    pub const ALL: [ProjectionView; 3] = [
+        ProjectionView::Front,
+        ProjectionView::Side,
+        ProjectionView::Top,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ProjectionView::Front => tr("view.front"),
+            ProjectionView::Side => tr("view.side"),
+            ProjectionView::Top => tr("view.top"),
+        }
+    }
+
+    /// The face of the cubes that is visible from this view.
+    pub fn face(self) -> Face {
+        match self {
+            ProjectionView::Front => Face::PosX,
+            ProjectionView::Side => Face::PosY,
+            ProjectionView::Top => Face::Top,
+        }
+    }
+}
+
+/// Flat picture of a construction seen from one direction: for every cell of the picture,
+/// the colors of the closest visible face, if any.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Projection {
+    rows: usize,
+    columns: usize,
+    cells: Vec<Option<FaceColors>>,
+}
+
+impl Projection {
+    pub fn compute(grid: &CubeGrid, cubemap: &CubeMap, view: ProjectionView) -> Self {
+        let (rows, columns) = match view {
+            ProjectionView::Front => (grid.height(), grid.depth()),
+            ProjectionView::Side => (grid.height(), grid.width()),
+            ProjectionView::Top => (grid.width(), grid.depth()),
+        };
+        let mut projection = Projection {
+            rows,
+            columns,
+            cells: vec![None; rows * columns],
+        };
+
+        // walk the cubes from the farthest to the closest one, so that the closest face wins
+        let mut cubes: Vec<_> = grid.iter_cubes().collect();
+        match view {
+            ProjectionView::Front => cubes.sort_by_key(|&(x, _, _, _)| x),
+            ProjectionView::Side => cubes.sort_by_key(|&(_, y, _, _)| y),
+            ProjectionView::Top => cubes.sort_by_key(|&(_, _, z, _)| z),
+        }
+        for (x, y, z, cube) in cubes {
+            let (row, column) = match view {
+                ProjectionView::Front => (grid.height() - 1 - z, y),
+                ProjectionView::Side => (grid.height() - 1 - z, grid.width() - 1 - x),
+                ProjectionView::Top => (x, y),
+            };
+            let face = cubemap.description(cube).face(view.face());
+            projection.cells[row * columns + column] = Some(face);
+        }
+
+        projection
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Option<FaceColors> {
+        self.cells[row * self.columns + column]
+    }
+}
+
+/// Paints the projection as a grid of colored squares filling the given rect.
+pub fn draw_projection(ui: &mut egui::Ui, projection: &Projection, rect: egui::Rect) {
+    if !ui.is_rect_visible(rect) {
+        return;
+    }
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, egui::Color32::DARK_GRAY);
+    let cell_size = egui::vec2(
+        rect.width() / projection.columns() as f32,
+        rect.height() / projection.rows() as f32,
+    );
+    for row in 0..projection.rows() {
+        for column in 0..projection.columns() {
+            let min = rect.min + cell_size * egui::vec2(column as f32, row as f32);
+            let cell = egui::Rect::from_min_size(min, cell_size).shrink(1.0);
+            match projection.get(row, column) {
+                Some(face) if face.is_solid() => {
+                    painter.rect_filled(cell, 0.0, face.0[0]);
+                }
+                Some(FaceColors(colors)) => {
+                    let corners = [cell.left_top(), cell.right_top(), cell.right_bottom(), cell.left_bottom()];
+                    for (i, color) in colors.iter().enumerate() {
+                        let triangle = vec![corners[i], corners[(i + 1) % 4], cell.center()];
+                        painter.add(egui::Shape::convex_polygon(triangle, *color, egui::Stroke::none()));
+                    }
+                }
+                None => {
+                    painter.rect_filled(cell, 0.0, egui::Color32::GRAY);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Language;
+use crate::storage;
+use crate::support::SupportRule;
+use crate::transition::DEFAULT_TRANSITION_DURATION;
+use crate::CameraView;
+
+const SETTINGS_NAME: &str = "settings";
+
+/// Preferences kept from one session to the next. Fields missing from the stored settings,
+/// e.g. because they were written by an older version, take their default value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// None follows the language of the system
+    pub language: Option<Language>,
+    pub show_paving: bool,
+    pub hide_builder: bool,
+    pub show_projections: bool,
+    pub camera_view: CameraView,
+    pub perspective: bool,
+    pub quad_view: bool,
+    pub transition_duration: f32,
+    pub support_rule: SupportRule,
+    /// Size of the window, in pixels
+    pub window_width: i32,
+    pub window_height: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            language: None,
+            show_paving: true,
+            hide_builder: false,
+            show_projections: false,
+            camera_view: CameraView::Isometric,
+            perspective: false,
+            quad_view: false,
+            transition_duration: DEFAULT_TRANSITION_DURATION,
+            support_rule: SupportRule::Gravity,
+            window_width: 800,
+            window_height: 600,
+        }
+    }
+}
+
+/// Reads the settings of the last session, falling back to the defaults the first time
+/// or when they cannot be read.
+pub fn load_settings() -> Settings {
+    storage::load_ron(SETTINGS_NAME)
+}
+
+pub fn save_settings(settings: &Settings) {
+    storage::save_ron(SETTINGS_NAME, settings)
+}
@@ -0,0 +1,106 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// On the desktop every document is a file in the configuration directory of the user.
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::path::PathBuf;
+
+    fn document_path(name: &str) -> Result<PathBuf, String> {
+        let config_dir = dirs::config_dir().ok_or("there is no configuration directory")?;
+        Ok(config_dir.join("lacu").join(name).with_extension("ron"))
+    }
+
+    pub fn read(name: &str) -> Result<Option<String>, String> {
+        let path = document_path(name)?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Ok(Some(text)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(format!("{}: {}", path.display(), err)),
+        }
+    }
+
+    pub fn write(name: &str, text: &str) -> Result<(), String> {
+        let path = document_path(name)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| format!("{}: {}", parent.display(), err))?;
+        }
+        std::fs::write(&path, text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+}
+
+/// In the browser the documents are kept in the local storage, through the functions
+/// that `storage.js` adds to the wasm imports.
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    extern "C" {
+        fn lacu_storage_get(key: *const u8, key_len: u32) -> i32;
+        fn lacu_storage_take(buffer: *mut u8);
+        fn lacu_storage_set(key: *const u8, key_len: u32, value: *const u8, value_len: u32);
+    }
+
+    /// Lets the JavaScript side check that it matches this version of the functions.
+    #[no_mangle]
+    pub extern "C" fn lacu_storage_crate_version() -> u32 {
+        1 << 24
+    }
+
+    fn key(name: &str) -> String {
+        format!("lacu.{}", name)
+    }
+
+    pub fn read(name: &str) -> Result<Option<String>, String> {
+        let key = key(name);
+        // the value is first measured, then copied into a buffer of the right size
+        let len = unsafe { lacu_storage_get(key.as_ptr(), key.len() as u32) };
+        if len < 0 {
+            return Ok(None);
+        }
+        let mut buffer = vec![0u8; len as usize];
+        unsafe { lacu_storage_take(buffer.as_mut_ptr()) };
+        String::from_utf8(buffer).map(Some).map_err(|err| err.to_string())
+    }
+
+    pub fn write(name: &str, text: &str) -> Result<(), String> {
+        let key = key(name);
+        unsafe { lacu_storage_set(key.as_ptr(), key.len() as u32, text.as_ptr(), text.len() as u32) };
+        Ok(())
+    }
+}
+
+/// Small documents kept on the computer of the user, like the settings, each one under its own name.
+/// Returns None when the document was never written.
+pub fn read(name: &str) -> Result<Option<String>, String> {
+    platform::read(name)
+}
+
+pub fn write(name: &str, text: &str) -> Result<(), String> {
+    platform::write(name, text)
+}
+
+/// Reads a document written by `save_ron`, falling back to the default value the first time
+/// or when it cannot be read. Failures are only logged, since the application works all the same.
+pub fn load_ron<T: DeserializeOwned + Default>(name: &str) -> T {
+    let text = match read(name) {
+        Ok(Some(text)) => text,
+        Ok(None) => return T::default(),
+        Err(err) => {
+            macroquad::logging::warn!("could not load the {}: {}", name, err);
+            return T::default();
+        }
+    };
+    ron::from_str(&text).unwrap_or_else(|err| {
+        macroquad::logging::warn!("could not read the {}: {}", name, err);
+        T::default()
+    })
+}
+
+/// Failures are only logged, like in `load_ron`.
+pub fn save_ron<T: Serialize>(name: &str, value: &T) {
+    let result = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|text| write(name, &text));
+    if let Err(err) = result {
+        macroquad::logging::warn!("could not save the {}: {}", name, err);
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::grid::CubeGrid;
+
+/// How a cube must be held up to stay in the construction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SupportRule {
+    /// Every cube must rest on the paving or on another cube.
+    Gravity,
+    /// Every cube must be linked to the paving through cubes sharing a face with it,
+    /// so overhangs are allowed.
+    TouchNeighbour,
+    /// Cubes can float anywhere in the grid.
+    Free,
+}
+
+impl SupportRule {
+    pub const ALL: [SupportRule; 3] = [SupportRule::Gravity, SupportRule::TouchNeighbour, SupportRule::Free];
+}
+
+const NEIGHBOUR_OFFSETS: [(i32, i32, i32); 6] = [
+    (1, 0, 0), (-1, 0, 0),
+    (0, 1, 0), (0, -1, 0),
+    (0, 0, 1), (0, 0, -1),
+];
+
+fn neighbours(grid: &CubeGrid, x: usize, y: usize, z: usize) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+    NEIGHBOUR_OFFSETS.iter().filter_map(move |&(dx, dy, dz)| {
+        let (nx, ny, nz) = (x as i32 + dx, y as i32 + dy, z as i32 + dz);
+        if nx < 0 || ny < 0 || nz < 0 || !grid.contains(nx as usize, ny as usize, nz as usize) {
+            return None;
+        }
+        Some((nx as usize, ny as usize, nz as usize))
+    })
+}
+
+/// Whether a cube placed in the given free cell would be supported according to the rule.
+pub fn can_place(grid: &CubeGrid, rule: SupportRule, x: usize, y: usize, z: usize) -> bool {
+    if !grid.contains(x, y, z) || grid.get(x, y, z).is_some() {
+        return false;
+    }
+    match rule {
+        SupportRule::Gravity => z == 0 || grid.get(x, y, z - 1).is_some(),
+        SupportRule::TouchNeighbour => {
+            // the neighbour is supported as long as the construction already follows the rule
+            z == 0 || neighbours(grid, x, y, z).any(|(nx, ny, nz)| grid.get(nx, ny, nz).is_some())
+        }
+        SupportRule::Free => true,
+    }
+}
+
+/// Cells of the cubes that break the rule, e.g. after the cube holding them up was removed.
+pub fn unsupported_cubes(grid: &CubeGrid, rule: SupportRule) -> Vec<(usize, usize, usize)> {
+    match rule {
+        SupportRule::Gravity => grid.iter_cubes()
+            .filter(|&(x, y, z, _)| grid.column(x, y)[..z].iter().any(|cell| cell.is_none()))
+            .map(|(x, y, z, _)| (x, y, z))
+            .collect(),
+        SupportRule::TouchNeighbour => {
+            // flood fill from the cubes resting on the paving
+            let mut supported = CubeGrid::new(grid.width(), grid.depth(), grid.height());
+            let mut queue: VecDeque<_> = grid.iter_cubes()
+                .filter(|&(_, _, z, _)| z == 0)
+                .collect();
+            for &(x, y, z, cube) in queue.iter() {
+                supported.set(x, y, z, Some(cube));
+            }
+            while let Some((x, y, z, _)) = queue.pop_front() {
+                for (nx, ny, nz) in neighbours(grid, x, y, z) {
+                    if let (Some(cube), None) = (grid.get(nx, ny, nz), supported.get(nx, ny, nz)) {
+                        supported.set(nx, ny, nz, Some(cube));
+                        queue.push_back((nx, ny, nz, cube));
+                    }
+                }
+            }
+            grid.iter_cubes()
+                .filter(|&(x, y, z, _)| supported.get(x, y, z).is_none())
+                .map(|(x, y, z, _)| (x, y, z))
+                .collect()
+        }
+        SupportRule::Free => Vec::new(),
+    }
+}
+
+/// Lets every cube fall down its column until it rests on the paving or on another cube.
+pub fn settled(grid: &CubeGrid) -> CubeGrid {
+    let mut settled = CubeGrid::new(grid.width(), grid.depth(), grid.height());
+    for x in 0..grid.width() {
+        for y in 0..grid.depth() {
+            let column = grid.column(x, y).iter().flatten();
+            for (z, cube) in column.enumerate() {
+                settled.set(x, y, z, Some(*cube));
+            }
+        }
+    }
+
+    settled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubes::{test_cubemap, Cube};
+
+    /// Grid of 3x3x3 cells with a red cube in each of the given ones.
+    fn grid_with(cells: &[(usize, usize, usize)]) -> (CubeGrid, Cube) {
+        let red = test_cubemap().find("Red").unwrap();
+        let mut grid = CubeGrid::new(3, 3, 3);
+        for &(x, y, z) in cells {
+            grid.set(x, y, z, Some(red));
+        }
+        (grid, red)
+    }
+
+    #[test]
+    fn gravity_needs_a_cube_below() {
+        let (grid, _) = grid_with(&[(0, 0, 0)]);
+        assert!(can_place(&grid, SupportRule::Gravity, 1, 1, 0));
+        assert!(can_place(&grid, SupportRule::Gravity, 0, 0, 1));
+        assert!(!can_place(&grid, SupportRule::Gravity, 1, 0, 1));
+        assert!(!can_place(&grid, SupportRule::Gravity, 0, 0, 0));
+        assert!(!can_place(&grid, SupportRule::Gravity, 0, 0, 3));
+
+        let (grid, _) = grid_with(&[(0, 0, 0), (0, 0, 2), (1, 0, 1)]);
+        assert_eq!(unsupported_cubes(&grid, SupportRule::Gravity), vec![(0, 0, 2), (1, 0, 1)]);
+    }
+
+    #[test]
+    fn touching_cubes_allow_overhangs() {
+        let (grid, _) = grid_with(&[(0, 0, 0), (0, 0, 1)]);
+        assert!(can_place(&grid, SupportRule::TouchNeighbour, 1, 0, 1));
+        assert!(can_place(&grid, SupportRule::TouchNeighbour, 2, 2, 0));
+        assert!(!can_place(&grid, SupportRule::TouchNeighbour, 1, 1, 1));
+        assert!(!can_place(&grid, SupportRule::TouchNeighbour, 0, 0, 1));
+
+        // the floating pair touches itself but nothing linked to the paving
+        let (grid, _) = grid_with(&[(0, 0, 0), (0, 0, 1), (1, 0, 1), (2, 0, 1), (2, 2, 1), (2, 2, 2)]);
+        assert_eq!(unsupported_cubes(&grid, SupportRule::TouchNeighbour), vec![(2, 2, 1), (2, 2, 2)]);
+    }
+
+    #[test]
+    fn free_cubes_float_anywhere() {
+        let (grid, _) = grid_with(&[(1, 1, 2)]);
+        assert!(can_place(&grid, SupportRule::Free, 2, 2, 2));
+        assert!(!can_place(&grid, SupportRule::Free, 1, 1, 2));
+        assert!(unsupported_cubes(&grid, SupportRule::Free).is_empty());
+    }
+
+    #[test]
+    fn settled_cubes_fall_down_their_column() {
+        let (grid, red) = grid_with(&[(0, 0, 2), (1, 1, 1), (1, 1, 2)]);
+        let settled = settled(&grid);
+        assert_eq!(settled.column(0, 0), &[Some(red), None, None]);
+        assert_eq!(settled.column(1, 1), &[Some(red), Some(red), None]);
+        assert_eq!(settled.iter_cubes().count(), 3);
+        assert!(unsupported_cubes(&settled, SupportRule::Gravity).is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+use macroquad::prelude::*;
+
+/// Seconds taken to move between two camera presets, unless configured otherwise
+pub const DEFAULT_TRANSITION_DURATION: f32 = 0.6;
+
+/// Animation of the camera from one preset to another. The camera turns around its target
+/// instead of going straight through the construction.
+pub struct CameraTransition {
+    from: Camera3D,
+    to: Camera3D,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl CameraTransition {
+    pub fn new(from: Camera3D, to: Camera3D, duration: f32) -> Self {
+        CameraTransition {
+            from,
+            to,
+            elapsed: 0.0,
+            duration,
+        }
+    }
+
+    /// Advances the animation and moves the camera accordingly.
+    /// Returns true once the camera has reached the goal.
+    pub fn update(&mut self, camera: &mut Camera3D, delta: f32) -> bool {
+        self.elapsed += delta;
+        let progress = if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        };
+        // ease in and out, so that the movement does not start or stop abruptly
+        let t = progress * progress * (3.0 - 2.0 * progress);
+
+        let from_offset = self.from.position - self.from.target;
+        let to_offset = self.to.position - self.to.target;
+        let offset_rotation = Quat::IDENTITY.slerp(Quat::from_rotation_arc(from_offset.normalize(), to_offset.normalize()), t);
+        let up_rotation = Quat::IDENTITY.slerp(Quat::from_rotation_arc(self.from.up.normalize(), self.to.up.normalize()), t);
+        let distance = from_offset.length() + (to_offset.length() - from_offset.length()) * t;
+
+        camera.target = self.from.target.lerp(self.to.target, t);
+        camera.position = camera.target + distance * (offset_rotation * from_offset.normalize());
+        camera.up = up_rotation * self.from.up.normalize();
+        camera.fovy = self.from.fovy + (self.to.fovy - self.from.fovy) * t;
+        camera.projection = self.to.projection;
+
+        progress >= 1.0
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use egui::{Color32, Shape};
+
+use crate::cubes::{face_shape, CubeMap};
+use crate::grid::CubeGrid;
+use crate::i18n::tr;
+use crate::projection::{Projection, ProjectionView};
+
+/// Sizes of an A4 page, in millimeters
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+/// Space between the pictures of the page
+const GAP: f32 = 10.0;
+/// Biggest side of a cell, so that small grids do not become huge
+const MAX_CELL_SIZE: f32 = 15.0;
+/// Height taken by the label above every picture
+const LABEL_HEIGHT: f32 = 7.0;
+/// How much a cube shrinks in the schema for every cube below it, relative to the cell
+const SCHEMA_SHRINK: f32 = 0.1;
+
+/// Printable A4 page, as an SVG document: the schema of the construction seen from above,
+/// its three orthographic views, and an empty schema where the student draws the answer.
+pub fn worksheet_svg(grid: &CubeGrid, cubemap: &CubeMap) -> String {
+    let mut svg = svg_start(PAGE_WIDTH, PAGE_HEIGHT);
+
+    let content_width = PAGE_WIDTH - 2.0 * MARGIN;
+    let mut top = MARGIN;
+    label(&mut svg, MARGIN, top + 6.0, 8.0, tr("worksheet.title"));
+    top += 15.0;
+
+    // the schema and the answer share the same size, so that they can be compared
+    let schema_cell = (content_width / grid.depth() as f32)
+        .min(70.0 / grid.width() as f32)
+        .min(MAX_CELL_SIZE);
+    label(&mut svg, MARGIN, top + 5.0, 5.0, tr("worksheet.schema"));
+    top += LABEL_HEIGHT;
+    draw_schema(&mut svg, grid, cubemap, MARGIN, top, schema_cell);
+    top += schema_cell * grid.width() as f32 + GAP;
+
+    let projections: Vec<_> = ProjectionView::ALL.iter()
+        .map(|&view| (view, Projection::compute(grid, cubemap, view)))
+        .collect();
+    let total_columns: usize = projections.iter().map(|(_, projection)| projection.columns()).sum();
+    let max_rows = projections.iter().map(|(_, projection)| projection.rows()).max().unwrap_or(1);
+    let view_cell = ((content_width - 2.0 * GAP) / total_columns as f32)
+        .min(60.0 / max_rows as f32)
+        .min(MAX_CELL_SIZE);
+    let mut left = MARGIN;
+    for (view, projection) in projections.iter() {
+        label(&mut svg, left, top + 5.0, 5.0, view.name());
+        draw_projection(&mut svg, projection, left, top + LABEL_HEIGHT, view_cell);
+        left += view_cell * projection.columns() as f32 + GAP;
+    }
+    top += LABEL_HEIGHT + view_cell * max_rows as f32 + GAP;
+
+    label(&mut svg, MARGIN, top + 5.0, 5.0, tr("worksheet.answer"));
+    top += LABEL_HEIGHT;
+    for x in 0..grid.width() {
+        for y in 0..grid.depth() {
+            let (cell_left, cell_top) = (MARGIN + y as f32 * schema_cell, top + x as f32 * schema_cell);
+            empty_cell(&mut svg, cell_left, cell_top, schema_cell);
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Just the three orthographic views of the construction, side by side, with one centimeter cells.
+pub fn projections_svg(grid: &CubeGrid, cubemap: &CubeMap) -> String {
+    let cell = 10.0;
+    let projections: Vec<_> = ProjectionView::ALL.iter()
+        .map(|&view| (view, Projection::compute(grid, cubemap, view)))
+        .collect();
+    let total_columns: usize = projections.iter().map(|(_, projection)| projection.columns()).sum();
+    let max_rows = projections.iter().map(|(_, projection)| projection.rows()).max().unwrap_or(1);
+    let width = 2.0 * MARGIN + cell * total_columns as f32 + GAP * (projections.len() - 1) as f32;
+    let height = 2.0 * MARGIN + LABEL_HEIGHT + cell * max_rows as f32;
+
+    let mut svg = svg_start(width, height);
+    let mut left = MARGIN;
+    for (view, projection) in projections.iter() {
+        label(&mut svg, left, MARGIN + 5.0, 5.0, view.name());
+        draw_projection(&mut svg, projection, left, MARGIN + LABEL_HEIGHT, cell);
+        left += cell * projection.columns() as f32 + GAP;
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+pub fn save_worksheet(path: impl AsRef<Path>, grid: &CubeGrid, cubemap: &CubeMap) -> std::io::Result<()> {
+    std::fs::write(path, worksheet_svg(grid, cubemap))
+}
+
+/// Same picture as the builder schema: every column is a square, and the cubes stacked in it
+/// get smaller and smaller going up.
+fn draw_schema(svg: &mut String, grid: &CubeGrid, cubemap: &CubeMap, left: f32, top: f32, cell: f32) {
+    for x in 0..grid.width() {
+        for y in 0..grid.depth() {
+            let (cell_left, cell_top) = (left + y as f32 * cell, top + x as f32 * cell);
+            empty_cell(svg, cell_left, cell_top, cell);
+            for (z, column_cell) in grid.column(x, y).iter().enumerate() {
+                if let Some(cube) = *column_cell {
+                    let shrink = SCHEMA_SHRINK * cell * z as f32;
+                    let size = cell - 2.0 * shrink;
+                    draw_shape(svg, &cubemap.top_shape(cube), cell_left + shrink, cell_top + shrink, size);
+                }
+            }
+        }
+    }
+}
+
+fn draw_projection(svg: &mut String, projection: &Projection, left: f32, top: f32, cell: f32) {
+    for row in 0..projection.rows() {
+        for column in 0..projection.columns() {
+            let (cell_left, cell_top) = (left + column as f32 * cell, top + row as f32 * cell);
+            match projection.get(row, column) {
+                Some(face) => draw_shape(svg, &face_shape(face), cell_left, cell_top, cell),
+                None => empty_cell(svg, cell_left, cell_top, cell),
+            }
+            outline(svg, cell_left, cell_top, cell);
+        }
+    }
+}
+
+/// Writes a shape built in the unit square, like the ones of `face_shape`, scaled to the cell.
+fn draw_shape(svg: &mut String, shape: &Shape, left: f32, top: f32, size: f32) {
+    match shape {
+        Shape::Rect(rect_shape) => {
+            let rect = rect_shape.rect;
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                left + rect.min.x * size,
+                top + rect.min.y * size,
+                rect.width() * size,
+                rect.height() * size,
+                fill(rect_shape.fill),
+            ).unwrap();
+        }
+        Shape::Path(path_shape) => {
+            let points: Vec<String> = path_shape.points.iter()
+                .map(|point| format!("{},{}", left + point.x * size, top + point.y * size))
+                .collect();
+            writeln!(svg, r#"<polygon points="{}" {}/>"#, points.join(" "), fill(path_shape.fill)).unwrap();
+        }
+        Shape::Vec(shapes) => {
+            for shape in shapes {
+                draw_shape(svg, shape, left, top, size);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Opens an SVG document measured in millimeters, with a white background.
+fn svg_start(width: f32, height: f32) -> String {
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}mm" height="{h}mm" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height,
+    ).unwrap();
+    svg.push_str(r#"<rect width="100%" height="100%" fill="white"/>"#);
+    svg.push('\n');
+
+    svg
+}
+
+fn empty_cell(svg: &mut String, left: f32, top: f32, size: f32) {
+    writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="white" stroke="gray" stroke-width="0.3"/>"#,
+        left,
+        top,
+        s = size,
+    ).unwrap();
+}
+
+fn outline(svg: &mut String, left: f32, top: f32, size: f32) {
+    writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="none" stroke="gray" stroke-width="0.3"/>"#,
+        left,
+        top,
+        s = size,
+    ).unwrap();
+}
+
+fn label(svg: &mut String, left: f32, baseline: f32, size: f32, text: &str) {
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}">{}</text>"#,
+        left,
+        baseline,
+        size,
+        text.replace('&', "&amp;").replace('<', "&lt;"),
+    ).unwrap();
+}
+
+fn fill(color: Color32) -> String {
+    let mut attributes = format!(r##"fill="#{:02x}{:02x}{:02x}""##, color.r(), color.g(), color.b());
+    if color.a() < 255 {
+        write!(attributes, r#" fill-opacity="{}""#, color.a() as f32 / 255.0).unwrap();
+    }
+    attributes
+}
@@ -0,0 +1,45 @@
+// keeps the settings and the progress of lacu in the local storage of the browser
+// must be loaded after gl_source.js and before the wasm module
+
+"use strict";
+
+(function () {
+    // value read by lacu_storage_get, waiting to be copied by lacu_storage_take
+    var pending = null;
+
+    function readString(ptr, len) {
+        return new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+    }
+
+    miniquad_add_plugin({
+        name: "lacu_storage",
+        version: "1.0.0",
+        register_plugin: function (importObject) {
+            importObject.env.lacu_storage_get = function (key_ptr, key_len) {
+                var value = null;
+                try {
+                    value = window.localStorage.getItem(readString(key_ptr, key_len));
+                } catch (e) {
+                    console.warn("local storage is not available: " + e);
+                }
+                if (value === null) {
+                    pending = null;
+                    return -1;
+                }
+                pending = new TextEncoder().encode(value);
+                return pending.length;
+            };
+            importObject.env.lacu_storage_take = function (buffer_ptr) {
+                new Uint8Array(wasm_memory.buffer, buffer_ptr, pending.length).set(pending);
+                pending = null;
+            };
+            importObject.env.lacu_storage_set = function (key_ptr, key_len, value_ptr, value_len) {
+                try {
+                    window.localStorage.setItem(readString(key_ptr, key_len), readString(value_ptr, value_len));
+                } catch (e) {
+                    console.warn("local storage is not available: " + e);
+                }
+            };
+        }
+    });
+})();